@@ -0,0 +1,14 @@
+// Shared by the benches and, through a #[path] module, by the library tests
+use image::{DynamicImage, RgbImage};
+
+// Smooth gradients with some high-frequency detail, so the JPEG is realistic
+pub fn synthetic_photo(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        let detail = ((x * 7 + y * 13) % 31) as u8;
+        image::Rgb([
+            ((x * 255) / width) as u8 ^ detail,
+            ((y * 255) / height) as u8,
+            (((x + y) * 255) / (width + height)) as u8 ^ (detail >> 1),
+        ])
+    }))
+}
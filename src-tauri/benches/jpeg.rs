@@ -0,0 +1,53 @@
+// Compares the standard JPEG encoder with the mozjpeg backend on a 12-megapixel
+// photo-sized image: encode time, and the output size at equal quality settings.
+//
+// Run with: cargo bench --bench jpeg
+
+mod common;
+
+use bulk_image_optimizer_lib::jpeg::{self, JpegBackend, JpegOptions};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use common::synthetic_photo;
+
+const SOURCE_WIDTH: u32 = 4000;
+const SOURCE_HEIGHT: u32 = 3000;
+
+fn options(encoder: JpegBackend) -> JpegOptions {
+    JpegOptions {
+        encoder: Some(encoder),
+        ..Default::default()
+    }
+}
+
+fn bench_encoders(c: &mut Criterion) {
+    let photo = synthetic_photo(SOURCE_WIDTH, SOURCE_HEIGHT);
+
+    // Sizes are printed once up front; criterion only reports timings
+    for quality in [60, 75, 85] {
+        let standard = jpeg::encode_jpeg(&photo, quality, &options(JpegBackend::Standard)).unwrap();
+        let mozjpeg = jpeg::encode_jpeg(&photo, quality, &options(JpegBackend::Mozjpeg)).unwrap();
+        println!(
+            "quality {}: standard {} KiB, mozjpeg {} KiB ({:.1}% smaller)",
+            quality,
+            standard.len() / 1024,
+            mozjpeg.len() / 1024,
+            100.0 * (1.0 - mozjpeg.len() as f64 / standard.len() as f64),
+        );
+        assert!(mozjpeg.len() < standard.len(), "mozjpeg output should be smaller");
+    }
+
+    let mut group = c.benchmark_group("encode_jpeg_12mp");
+    group.sample_size(10);
+
+    for encoder in [JpegBackend::Standard, JpegBackend::Mozjpeg] {
+        let id = BenchmarkId::new(format!("{:?}", encoder), 75);
+        let options = options(encoder);
+        group.bench_with_input(id, &options, |b, options| {
+            b.iter(|| jpeg::encode_jpeg(&photo, 75, options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encoders);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+// Compares the standard image-crate resize path with the fast backend on a
+// 24-megapixel photo-sized image, plus JPEG shrink-on-load.
+//
+// Run with: cargo bench --bench resize
+
+mod common;
+
+use bulk_image_optimizer_lib::decode;
+use bulk_image_optimizer_lib::resize::{self, ResizeBackend, ResizeFilter};
+use bulk_image_optimizer_lib::{OperationMode, ProcessingSettings, ResizeMode};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use common::synthetic_photo;
+use image::GenericImageView;
+
+const SOURCE_WIDTH: u32 = 6000;
+const SOURCE_HEIGHT: u32 = 4000;
+
+fn settings(backend: ResizeBackend, filter: ResizeFilter) -> ProcessingSettings {
+    ProcessingSettings {
+        operation_mode: OperationMode::Resize,
+        resize_mode: Some(ResizeMode::LongEdge),
+        resize_length: Some(1600),
+        resize_filter: Some(filter),
+        resize_backend: Some(backend),
+        ..Default::default()
+    }
+}
+
+fn bench_resize(c: &mut Criterion) {
+    let photo = synthetic_photo(SOURCE_WIDTH, SOURCE_HEIGHT);
+    let mut group = c.benchmark_group("resize_24mp_to_1600");
+    group.sample_size(10);
+
+    for filter in [ResizeFilter::Triangle, ResizeFilter::Lanczos3] {
+        for backend in [ResizeBackend::Standard, ResizeBackend::Fast] {
+            let id = BenchmarkId::new(format!("{:?}", backend), format!("{:?}", filter));
+            let settings = settings(backend, filter);
+            group.bench_with_input(id, &settings, |b, settings| {
+                b.iter(|| resize::resize_image(photo.clone(), settings).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_shrink_on_load(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join("optisnap-bench");
+    std::fs::create_dir_all(&dir).unwrap();
+    let jpeg_path = dir.join("photo.jpg");
+    synthetic_photo(SOURCE_WIDTH, SOURCE_HEIGHT).save(&jpeg_path).unwrap();
+
+    let mut group = c.benchmark_group("decode_and_resize_jpeg");
+    group.sample_size(10);
+
+    for backend in [ResizeBackend::Standard, ResizeBackend::Fast] {
+        let settings = settings(backend, ResizeFilter::Lanczos3);
+        let limits = decode::DecodeLimits::from_settings(&settings);
+        group.bench_function(format!("{:?}", backend), |b| {
+            b.iter(|| {
+                let source = image::image_dimensions(&jpeg_path).unwrap();
+                let plan = resize::plan_resize(source, &settings).unwrap();
+                let hint = match backend {
+                    ResizeBackend::Fast => plan.decode_hint(source),
+                    ResizeBackend::Standard => None,
+                };
+                let img = decode::load_image(&jpeg_path, hint, &limits).unwrap();
+                let outcome = resize::apply_plan(img, &plan, source, &settings);
+                assert_eq!(outcome.image.dimensions().0, 1600);
+                outcome
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_resize, bench_shrink_on_load);
+criterion_main!(benches);
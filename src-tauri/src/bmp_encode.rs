@@ -0,0 +1,116 @@
+use image::{ColorType, DynamicImage, GenericImageView, ImageFormat};
+use std::fs;
+use std::path::Path;
+
+use crate::reduce::{self, ColorAnalysis};
+
+const FILE_HEADER_SIZE: u32 = 14;
+const INFO_HEADER_SIZE: u32 = 40;
+// 72 DPI
+const PIXELS_PER_METER: i32 = 2835;
+
+// BMP palettes have no alpha and no 2-bit layout
+fn indexed_bmp(img: &DynamicImage, palette: &[[u8; 4]]) -> Vec<u8> {
+    let (width, height) = img.dimensions();
+    let mut indexed = reduce::to_indexed(img, palette);
+    if indexed.bit_depth == 2 {
+        indexed.bit_depth = 4;
+    }
+    let bit_depth = indexed.bit_depth as u32;
+
+    let packed = reduce::pack_rows(&indexed, width);
+    let packed_row = (width * bit_depth).div_ceil(8) as usize;
+    // Rows are padded to whole 32-bit words
+    let stride = (width * bit_depth).div_ceil(32) as usize * 4;
+    let palette_size = 4 * palette.len() as u32;
+    let data_offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE + palette_size;
+    let image_size = (stride * height as usize) as u32;
+
+    let mut bmp = Vec::with_capacity((data_offset + image_size) as usize);
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(data_offset + image_size).to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    bmp.extend_from_slice(&data_offset.to_le_bytes());
+
+    bmp.extend_from_slice(&INFO_HEADER_SIZE.to_le_bytes());
+    bmp.extend_from_slice(&(width as i32).to_le_bytes());
+    bmp.extend_from_slice(&(height as i32).to_le_bytes()); // Positive height: rows stored bottom-up
+    bmp.extend_from_slice(&1u16.to_le_bytes()); // Planes
+    bmp.extend_from_slice(&(bit_depth as u16).to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes()); // No compression
+    bmp.extend_from_slice(&image_size.to_le_bytes());
+    bmp.extend_from_slice(&PIXELS_PER_METER.to_le_bytes());
+    bmp.extend_from_slice(&PIXELS_PER_METER.to_le_bytes());
+    bmp.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes()); // All colors important
+
+    for [r, g, b, _] in palette {
+        bmp.extend_from_slice(&[*b, *g, *r, 0]);
+    }
+
+    for row in packed.chunks(packed_row).rev() {
+        bmp.extend_from_slice(row);
+        bmp.resize(bmp.len() + stride - packed_row, 0);
+    }
+    bmp
+}
+
+// Write a BMP in its smallest layout: 1, 4 or 8-bit palette for opaque images
+// with few colors, otherwise the 8-bit image crate encoder
+pub fn save_bmp(img: &DynamicImage, output_path: &Path, analysis: Option<&ColorAnalysis>) -> Result<(), String> {
+    let palette = analysis
+        .filter(|analysis| analysis.opaque)
+        .and_then(|analysis| analysis.palette.as_deref());
+    if let Some(palette) = palette {
+        return fs::write(output_path, indexed_bmp(img, palette))
+            .map_err(|e| format!("Failed to write BMP file: {}", e));
+    }
+
+    // The BMP encoder only takes 8-bit samples
+    let converted = match img.color() {
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => None,
+        ColorType::L16 => Some(DynamicImage::ImageLuma8(img.to_luma8())),
+        ColorType::La16 => Some(DynamicImage::ImageLumaA8(img.to_luma_alpha8())),
+        color if color.has_alpha() => Some(DynamicImage::ImageRgba8(img.to_rgba8())),
+        _ => Some(DynamicImage::ImageRgb8(img.to_rgb8())),
+    };
+    converted.as_ref().unwrap_or(img)
+        .save_with_format(output_path, ImageFormat::Bmp)
+        .map_err(|e| format!("Failed to save image: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    // Opaque image cycling through `colors` distinct colors
+    fn image_with_colors(width: u32, height: u32, colors: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let index = (x * 3 + y * 5) % colors;
+            image::Rgb([(index * 37) as u8, (index * 11) as u8, 255 - index as u8])
+        }))
+    }
+
+    fn round_trip(img: &DynamicImage) -> (u16, DynamicImage) {
+        let palette = reduce::analyze(img).palette.unwrap();
+        let bmp = indexed_bmp(img, &palette);
+        let bit_depth = u16::from_le_bytes([bmp[28], bmp[29]]);
+        let decoded = image::load_from_memory_with_format(&bmp, ImageFormat::Bmp).unwrap();
+        (bit_depth, decoded)
+    }
+
+    #[test]
+    fn indexed_bmp_round_trips() {
+        // Two-bit palettes are widened to four bits
+        for (colors, expected_depth) in [(2, 1), (3, 4), (16, 4), (200, 8)] {
+            for width in [1, 7, 13, 33] {
+                let img = image_with_colors(width, 40, colors);
+                let (bit_depth, decoded) = round_trip(&img);
+                assert_eq!(bit_depth, expected_depth, "{} colors", colors);
+                assert_eq!(decoded.dimensions(), (width, 40));
+                assert_eq!(decoded.to_rgb8(), img.to_rgb8(), "{} colors, width {}", colors, width);
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::resize::ResizeDecision;
+use crate::smartcrop::FocalPoint;
+use crate::ProcessingSettings;
+
+// Bump whenever encoder output changes for identical settings, so stale
+// entries from older builds are never treated as up-to-date
+const CACHE_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub output_path: String,
+    pub output_size: u64,
+    pub output_modified: SystemTime, // Full precision, so same-second rewrites are noticed
+    pub output_width: u32,
+    pub output_height: u32,
+    #[serde(default)]
+    pub focal_point: Option<FocalPoint>,
+    #[serde(default)]
+    pub resize_decision: Option<ResizeDecision>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl CacheEntry {
+    // Whether the output on disk is still exactly what we wrote
+    fn is_current(&self) -> bool {
+        file_stamp(Path::new(&self.output_path))
+            .is_some_and(|(size, modified)| size == self.output_size && modified == self.output_modified)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<String, CacheEntry>,
+}
+
+// Persistent map from (source file + content hash + effective settings) to the
+// output that was produced for it
+pub struct ProcessingCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl ProcessingCache {
+    pub fn load(path: PathBuf) -> Self {
+        // A missing or unreadable cache simply starts empty
+        let mut entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<CacheFile>(&data).ok())
+            .filter(|file| file.version == CACHE_VERSION)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        // Entries whose output was deleted or replaced can never hit again;
+        // dropping them keeps the file from growing with every new batch
+        entries.retain(|_, entry| entry.is_current());
+
+        ProcessingCache {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub fn lookup(&self, key: &str) -> Option<CacheEntry> {
+        let entries = self.entries.lock().ok()?;
+        entries.get(key)
+            .filter(|entry| entry.is_current())
+            .cloned()
+    }
+
+    pub fn insert(&self, key: String, entry: CacheEntry) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, entry);
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+        self.save()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let entries = self.entries.lock()
+            .map_err(|_| "Cache lock poisoned".to_string())?;
+
+        let data = serde_json::to_string(&CacheFile {
+            version: CACHE_VERSION,
+            entries: entries.clone(),
+        })
+        .map_err(|e| format!("Failed to serialize cache: {}", e))?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        fs::write(&self.path, data)
+            .map_err(|e| format!("Failed to write cache: {}", e))?;
+
+        Ok(())
+    }
+}
+
+// Size and modification time of a file, used to detect outputs that were
+// edited or replaced after we wrote them
+pub fn file_stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open file for hashing: {}", e))?;
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to hash file: {}", e))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+// Cache key for a source file processed with the given settings. The source path
+// and output location are part of the key because together they decide where the
+// output lives: two files with identical bytes still need two outputs.
+pub fn cache_key(
+    source_path: &Path,
+    source_hash: &str,
+    output_dir: &Path,
+    overwrite: bool,
+    settings: &ProcessingSettings,
+) -> String {
+    let settings_json = serde_json::to_string(settings).unwrap_or_default();
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(source_path.to_string_lossy().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(source_hash.as_bytes());
+    hasher.update(output_dir.to_string_lossy().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(&[overwrite as u8]);
+    hasher.update(settings_json.as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_files_get_separate_entries() {
+        let dir = std::env::temp_dir().join(format!("optisnap-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.png"), dir.join("b.png"));
+        fs::write(&a, b"same bytes").unwrap();
+        fs::write(&b, b"same bytes").unwrap();
+        let a_output = dir.join("a_optimized.png");
+        fs::write(&a_output, b"output").unwrap();
+
+        let settings = ProcessingSettings::default();
+        let key = |path: &Path| cache_key(path, &hash_file(path).unwrap(), &dir, false, &settings);
+        assert_ne!(key(&a), key(&b));
+
+        let cache = ProcessingCache::load(dir.join("cache.json"));
+        let (size, modified) = file_stamp(&a_output).unwrap();
+        cache.insert(key(&a), CacheEntry {
+            output_path: a_output.to_string_lossy().to_string(),
+            output_size: size,
+            output_modified: modified,
+            output_width: 1,
+            output_height: 1,
+            focal_point: None,
+            resize_decision: None,
+            warnings: Vec::new(),
+        });
+
+        assert!(cache.lookup(&key(&a)).is_some());
+        assert!(cache.lookup(&key(&b)).is_none());
+
+        // Rewritten with the same length within the same second
+        let rewritten = modified + std::time::Duration::from_millis(1);
+        fs::write(&a_output, b"OUTPUT").unwrap();
+        fs::File::options().write(true).open(&a_output).unwrap().set_modified(rewritten).unwrap();
+        assert!(cache.lookup(&key(&a)).is_none());
+
+        // The stale entry is saved, but dropped on the next load
+        cache.save().unwrap();
+        let reloaded = ProcessingCache::load(dir.join("cache.json"));
+        assert!(reloaded.entries.lock().unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
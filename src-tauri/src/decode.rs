@@ -0,0 +1,251 @@
+use image::{DynamicImage, GrayImage, ImageDecoder, ImageError, ImageReader, RgbImage};
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::svg::{self, SvgOptions};
+use crate::{heif, ErrorCode, OutputFormat, ProcessError, ProcessingSettings};
+
+// 250 megapixels: well above any camera, far below a decompression bomb
+const DEFAULT_MAX_PIXELS: u64 = 250_000_000;
+// Same as the image crate's own max_alloc; larger images need an explicit opt-in
+const DEFAULT_MAX_DECODE_BYTES: u64 = 512 * 1024 * 1024;
+
+// Per-image limits checked against the header before any pixels are decoded
+#[derive(Debug, Clone, Copy)]
+pub struct DecodeLimits {
+    pub max_pixels: u64,
+    pub max_decode_bytes: u64,
+}
+
+impl DecodeLimits {
+    pub fn from_settings(settings: &ProcessingSettings) -> Self {
+        DecodeLimits {
+            max_pixels: settings.max_pixels.unwrap_or(DEFAULT_MAX_PIXELS),
+            max_decode_bytes: settings.max_decode_bytes.unwrap_or(DEFAULT_MAX_DECODE_BYTES),
+        }
+    }
+
+    fn to_image_limits(self) -> image::Limits {
+        let mut limits = image::Limits::default();
+        limits.max_alloc = Some(self.max_decode_bytes);
+        limits
+    }
+}
+
+// What the header says about an image, read without decoding pixels
+#[derive(Debug, Clone, Copy)]
+pub struct SourceHeader {
+    pub width: u32,
+    pub height: u32,
+    pub bytes_per_pixel: u64,
+    pub has_alpha: bool,
+}
+
+impl SourceHeader {
+    pub fn pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn decoded_bytes(&self) -> u64 {
+        self.pixels() * self.bytes_per_pixel
+    }
+}
+
+// SVG sizes depend on the raster options, other formats are read as stored
+pub fn read_header(path: &Path, svg_options: &SvgOptions) -> Result<SourceHeader, ProcessError> {
+    if svg::is_svg(path) {
+        return svg::read_header(path, svg_options);
+    }
+    if heif::is_heif(path) {
+        return heif::read_header(path);
+    }
+
+    let decoder = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .into_decoder()
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    let (width, height) = decoder.dimensions();
+
+    Ok(SourceHeader {
+        width,
+        height,
+        bytes_per_pixel: decoder.color_type().bytes_per_pixel() as u64,
+        has_alpha: decoder.color_type().has_alpha(),
+    })
+}
+
+// Width and height for display, without decoding any pixels
+pub fn image_dimensions(path: &Path) -> Result<(u32, u32), String> {
+    if svg::is_svg(path) {
+        return svg::intrinsic_size(path);
+    }
+    if heif::is_heif(path) {
+        let header = heif::read_header(path).map_err(|e| e.message)?;
+        return Ok((header.width, header.height));
+    }
+    image::image_dimensions(path)
+        .map_err(|e| format!("Failed to open image: {}", e))
+}
+
+pub fn check_limits(header: &SourceHeader, limits: &DecodeLimits) -> Result<(), ProcessError> {
+    if header.pixels() > limits.max_pixels {
+        return Err(ProcessError::new(
+            ErrorCode::ImageTooLarge,
+            format!(
+                "Image is {}×{} ({} megapixels), above the limit of {} megapixels",
+                header.width,
+                header.height,
+                header.pixels() / 1_000_000,
+                limits.max_pixels / 1_000_000,
+            ),
+        ));
+    }
+    if header.decoded_bytes() > limits.max_decode_bytes {
+        return Err(ProcessError::new(
+            ErrorCode::DecodeLimitExceeded,
+            format!(
+                "Decoding needs {} MiB, above the limit of {} MiB",
+                header.decoded_bytes() / (1024 * 1024),
+                limits.max_decode_bytes / (1024 * 1024),
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Decode a JPEG at a reduced size using DCT scaling (1/2, 1/4 or 1/8), which
+// skips most of the IDCT work for large downscales. Returns None for pixel
+// formats this path doesn't handle, so the caller can fall back. This decoder
+// has no max_alloc, so the scaled size is checked against the byte limit here;
+// anything above it falls back to the full decode, which then fails the limit.
+fn decode_jpeg_scaled(path: &Path, min_size: (u32, u32), limits: &DecodeLimits) -> Option<DynamicImage> {
+    let file = fs::File::open(path).ok()?;
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(file));
+
+    // The decoder picks the smallest scale that is still at least this large
+    let requested_w = min_size.0.clamp(1, u16::MAX as u32) as u16;
+    let requested_h = min_size.1.clamp(1, u16::MAX as u32) as u16;
+    let (scaled_w, scaled_h) = decoder.scale(requested_w, requested_h).ok()?;
+    let bytes_per_pixel = decoder.info()?.pixel_format.pixel_bytes() as u64;
+    if scaled_w as u64 * scaled_h as u64 * bytes_per_pixel > limits.max_decode_bytes {
+        return None;
+    }
+
+    let pixels = decoder.decode().ok()?;
+    let info = decoder.info()?;
+    let (width, height) = (info.width as u32, info.height as u32);
+
+    match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => {
+            GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+        }
+        jpeg_decoder::PixelFormat::RGB24 => {
+            RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+        }
+        _ => None,
+    }
+}
+
+// Load an image for processing. `shrink_hint` is the smallest size the
+// pipeline needs; decoders that can shrink on load may return anything at
+// least that large instead of the full resolution. Callers are expected to
+// have checked the header against `limits` already; the allocation limit is
+// enforced again inside the decoder.
+pub fn load_image(
+    path: &Path,
+    shrink_hint: Option<(u32, u32)>,
+    limits: &DecodeLimits,
+) -> Result<DynamicImage, ProcessError> {
+    if let Some(min_size) = shrink_hint {
+        if let Some(OutputFormat::Jpeg) = OutputFormat::from_path(path) {
+            if let Some(img) = decode_jpeg_scaled(path, min_size, limits) {
+                return Ok(img);
+            }
+        }
+    }
+
+    if heif::is_heif(path) {
+        return heif::decode(path);
+    }
+
+    let mut reader = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    reader.limits(limits.to_image_limits());
+
+    reader.decode().map_err(|e| match e {
+        ImageError::Limits(limit) => ProcessError::new(
+            ErrorCode::DecodeLimitExceeded,
+            format!("Image exceeds decoding limits: {}", limit),
+        ),
+        e => format!("Failed to open image: {}", e).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: u32, height: u32, bytes_per_pixel: u64) -> SourceHeader {
+        SourceHeader { width, height, bytes_per_pixel, has_alpha: false }
+    }
+
+    fn limits(max_pixels: u64, max_decode_bytes: u64) -> DecodeLimits {
+        DecodeLimits { max_pixels, max_decode_bytes }
+    }
+
+    #[test]
+    fn images_within_the_limits_pass() {
+        assert!(check_limits(&header(1000, 1000, 4), &limits(1_000_000, 4_000_000)).is_ok());
+    }
+
+    #[test]
+    fn too_many_pixels_is_image_too_large() {
+        let error = check_limits(&header(1001, 1000, 1), &limits(1_000_000, u64::MAX)).unwrap_err();
+        assert!(matches!(error.code, Some(ErrorCode::ImageTooLarge)));
+    }
+
+    #[test]
+    fn too_many_decoded_bytes_is_decode_limit_exceeded() {
+        // 16-bit RGBA needs 8 bytes per pixel
+        let error = check_limits(&header(1000, 1000, 8), &limits(1_000_000, 4_000_000)).unwrap_err();
+        assert!(matches!(error.code, Some(ErrorCode::DecodeLimitExceeded)));
+    }
+
+    #[test]
+    fn defaults_come_from_the_settings() {
+        let settings = ProcessingSettings {
+            max_pixels: Some(10),
+            ..Default::default()
+        };
+        let limits = DecodeLimits::from_settings(&settings);
+        assert_eq!(limits.max_pixels, 10);
+        assert_eq!(limits.max_decode_bytes, DEFAULT_MAX_DECODE_BYTES);
+    }
+
+    #[test]
+    fn decoder_allocation_limit_is_decode_limit_exceeded() {
+        let path = std::env::temp_dir().join(format!("optisnap-decode-{}.png", std::process::id()));
+        image::RgbaImage::new(256, 256).save(&path).unwrap();
+        let result = load_image(&path, None, &limits(u64::MAX, 1024));
+        std::fs::remove_file(&path).ok();
+
+        let error = result.unwrap_err();
+        assert!(matches!(error.code, Some(ErrorCode::DecodeLimitExceeded)), "{}", error.message);
+    }
+
+    #[test]
+    fn shrink_on_load_respects_the_byte_limit() {
+        let path = std::env::temp_dir().join(format!("optisnap-decode-scaled-{}.jpg", std::process::id()));
+        RgbImage::new(256, 256).save(&path).unwrap();
+        // 1/4 scale is 64×64 RGB, 12 KiB
+        let scaled = load_image(&path, Some((64, 64)), &limits(u64::MAX, 16 * 1024));
+        let too_large = load_image(&path, Some((64, 64)), &limits(u64::MAX, 1024));
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(scaled.unwrap().width(), 64);
+        assert!(matches!(too_large.unwrap_err().code, Some(ErrorCode::DecodeLimitExceeded)));
+    }
+}
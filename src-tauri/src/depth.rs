@@ -0,0 +1,113 @@
+use image::{ColorType, DynamicImage};
+
+// Precision of a single sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SampleDepth {
+    Eight,
+    Sixteen,
+    Float, // 32-bit float, may hold values outside 0.0 - 1.0
+}
+
+impl SampleDepth {
+    pub fn of(color: ColorType) -> SampleDepth {
+        match color.bytes_per_pixel() / color.channel_count() {
+            1 => SampleDepth::Eight,
+            2 => SampleDepth::Sixteen,
+            _ => SampleDepth::Float,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            SampleDepth::Eight => "8-bit",
+            SampleDepth::Sixteen => "16-bit",
+            SampleDepth::Float => "32-bit float",
+        }
+    }
+}
+
+// Convert samples deeper than `max` down to `max`, keeping the channel layout.
+// Returns the depth the image had when it was reduced, None when it already fit.
+pub fn limit_depth(img: DynamicImage, max: SampleDepth) -> (DynamicImage, Option<SampleDepth>) {
+    let color = img.color();
+    let depth = SampleDepth::of(color);
+    if depth <= max {
+        return (img, None);
+    }
+
+    let gray = color.channel_count() <= 2;
+    let alpha = color.has_alpha();
+    let reduced = match (max, gray, alpha) {
+        (SampleDepth::Eight, true, false) => DynamicImage::ImageLuma8(img.to_luma8()),
+        (SampleDepth::Eight, true, true) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        (SampleDepth::Eight, false, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+        (SampleDepth::Eight, false, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+        // Only float images are deeper than 16 bits, and those are always color
+        (_, _, false) => DynamicImage::ImageRgb16(img.to_rgb16()),
+        (_, _, true) => DynamicImage::ImageRgba16(img.to_rgba16()),
+    };
+    (reduced, Some(depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma, LumaA, Rgb, Rgb32FImage, Rgba, Rgba32FImage};
+
+    fn reduced(img: DynamicImage, max: SampleDepth) -> (ColorType, Option<SampleDepth>) {
+        let (img, from) = limit_depth(img, max);
+        (img.color(), from)
+    }
+
+    #[test]
+    fn sixteen_bit_is_reduced_to_eight_keeping_the_layout() {
+        let gray = DynamicImage::ImageLuma16(ImageBuffer::from_pixel(2, 2, Luma([65535u16])));
+        let gray_alpha = DynamicImage::ImageLumaA16(ImageBuffer::from_pixel(2, 2, LumaA([0u16, 65535])));
+        let rgb = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(2, 2, Rgb([257 * 128u16, 0, 65535])));
+        let rgba = DynamicImage::ImageRgba16(ImageBuffer::from_pixel(2, 2, Rgba([0u16, 0, 0, 65535])));
+
+        let eight = SampleDepth::Eight;
+        let sixteen = Some(SampleDepth::Sixteen);
+        assert_eq!(reduced(gray, eight), (ColorType::L8, sixteen));
+        assert_eq!(reduced(gray_alpha, eight), (ColorType::La8, sixteen));
+        assert_eq!(reduced(rgb.clone(), eight), (ColorType::Rgb8, sixteen));
+        assert_eq!(reduced(rgba, eight), (ColorType::Rgba8, sixteen));
+
+        let (rgb8, _) = limit_depth(rgb, eight);
+        assert_eq!(rgb8.to_rgb8().get_pixel(0, 0).0, [128, 0, 255]);
+    }
+
+    #[test]
+    fn float_is_reduced_to_eight_or_sixteen() {
+        let rgb = DynamicImage::ImageRgb32F(Rgb32FImage::from_pixel(2, 2, Rgb([0.5, 2.0, -1.0])));
+        let rgba = DynamicImage::ImageRgba32F(Rgba32FImage::from_pixel(2, 2, Rgba([0.0, 0.0, 0.0, 0.5])));
+
+        let float = Some(SampleDepth::Float);
+        assert_eq!(reduced(rgb.clone(), SampleDepth::Eight), (ColorType::Rgb8, float));
+        assert_eq!(reduced(rgba.clone(), SampleDepth::Eight), (ColorType::Rgba8, float));
+        assert_eq!(reduced(rgb.clone(), SampleDepth::Sixteen), (ColorType::Rgb16, float));
+        assert_eq!(reduced(rgba, SampleDepth::Sixteen), (ColorType::Rgba16, float));
+
+        // Values outside 0.0 - 1.0 clip
+        let (rgb8, _) = limit_depth(rgb, SampleDepth::Eight);
+        assert_eq!(rgb8.to_rgb8().get_pixel(0, 0).0, [128, 255, 0]);
+    }
+
+    #[test]
+    fn images_that_fit_are_untouched() {
+        let rgb16 = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(2, 2, Rgb([1u16, 2, 3])));
+        let (img, from) = limit_depth(rgb16.clone(), SampleDepth::Sixteen);
+        assert_eq!((img, from), (rgb16.clone(), None));
+        assert_eq!(reduced(rgb16, SampleDepth::Float), (ColorType::Rgb16, None));
+    }
+
+    #[test]
+    fn depth_is_read_from_the_color_type() {
+        assert_eq!(SampleDepth::of(ColorType::La8), SampleDepth::Eight);
+        assert_eq!(SampleDepth::of(ColorType::Rgba16), SampleDepth::Sixteen);
+        assert_eq!(SampleDepth::of(ColorType::Rgb32F), SampleDepth::Float);
+        // The names used in the bit-depth warning
+        assert_eq!(SampleDepth::Sixteen.describe(), "16-bit");
+        assert_eq!(SampleDepth::Float.describe(), "32-bit float");
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use crate::{heif, OutputFormat};
+
+// Which decoder reads a format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    Raster, // The image crate
+    Hdr, // The image crate; pixels are scene-linear and may exceed 1.0, so they get tone mapped
+    Svg, // Rasterized with resvg
+    Heif, // libheif
+}
+
+// A file type the app reads when `source` is set, and writes when `output` is set
+pub struct FormatSpec {
+    pub extensions: &'static [&'static str], // Lowercase; the first one names output files
+    pub output: Option<OutputFormat>,
+    pub source: Option<SourceKind>,
+    pub enabled: bool, // False for formats behind a build feature that is off
+}
+
+// Every supported format in one place. The scanner, the watcher, the file
+// picker, decoder selection, output file names and get_supported_formats are
+// all derived from this table.
+pub const FORMATS: &[FormatSpec] = &[
+    FormatSpec { extensions: &["png"], output: Some(OutputFormat::Png), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["jpg", "jpeg"], output: Some(OutputFormat::Jpeg), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["webp"], output: Some(OutputFormat::Webp), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["tiff", "tif"], output: Some(OutputFormat::Tiff), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["qoi"], output: Some(OutputFormat::Qoi), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["bmp"], output: Some(OutputFormat::Bmp), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["tga"], output: Some(OutputFormat::Tga), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["pnm", "pbm", "pgm", "ppm", "pam"], output: Some(OutputFormat::Pnm), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["ico"], output: Some(OutputFormat::Ico), source: Some(SourceKind::Raster), enabled: true },
+    FormatSpec { extensions: &["gif"], output: Some(OutputFormat::Gif), source: Some(SourceKind::Raster), enabled: true }, // First frame only
+    FormatSpec { extensions: &["avif"], output: Some(OutputFormat::Avif), source: None, enabled: true }, // Output only, there is no pure Rust decoder
+    FormatSpec { extensions: &["dds"], output: None, source: Some(SourceKind::Raster), enabled: true }, // DXT1/3/5 textures
+    FormatSpec { extensions: &["exr"], output: None, source: Some(SourceKind::Hdr), enabled: true },
+    FormatSpec { extensions: &["hdr"], output: None, source: Some(SourceKind::Hdr), enabled: true },
+    FormatSpec { extensions: &["svg"], output: None, source: Some(SourceKind::Svg), enabled: true },
+    FormatSpec { extensions: &["heic", "heif"], output: None, source: Some(SourceKind::Heif), enabled: heif::ENABLED },
+];
+
+// Includes disabled formats, so their decoders can explain why they fail
+fn spec_for_path(path: &Path) -> Option<&'static FormatSpec> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    FORMATS.iter().find(|spec| spec.extensions.contains(&ext.as_str()))
+}
+
+pub fn is_supported_input(path: &Path) -> bool {
+    spec_for_path(path).is_some_and(|spec| spec.enabled && spec.source.is_some())
+}
+
+pub fn source_kind(path: &Path) -> Option<SourceKind> {
+    spec_for_path(path)?.source
+}
+
+// Whether the file is in the format known by `name`, which may be any of the
+// format's extensions: "jpeg" also matches .jpg files, "heif" .heic files
+pub fn is_format(path: &Path, name: &str) -> bool {
+    let name = name.trim_start_matches('.').to_lowercase();
+    spec_for_path(path).is_some_and(|spec| spec.extensions.contains(&name.as_str()))
+}
+
+pub fn output_for_path(path: &Path) -> Option<OutputFormat> {
+    spec_for_path(path).filter(|spec| spec.enabled)?.output.clone()
+}
+
+// What a file is written as when no output format is chosen: its own format,
+// or for input-only formats JPEG (HEIF photos) or PNG (lossless, keeps alpha).
+// Disabled formats get one too, so their decoders can explain why they fail.
+pub fn default_output(path: &Path) -> Option<OutputFormat> {
+    let spec = spec_for_path(path)?;
+    let fallback = match spec.source? {
+        SourceKind::Heif => OutputFormat::Jpeg,
+        SourceKind::Raster | SourceKind::Hdr | SourceKind::Svg => OutputFormat::Png,
+    };
+    Some(spec.output.clone().unwrap_or(fallback))
+}
+
+// Every extension this build reads, for the file picker and drop filter
+pub fn input_extensions() -> Vec<&'static str> {
+    FORMATS.iter()
+        .filter(|spec| spec.enabled && spec.source.is_some())
+        .flat_map(|spec| spec.extensions.iter().copied())
+        .collect()
+}
+
+pub fn output_extension(format: &OutputFormat) -> &'static str {
+    FORMATS.iter()
+        .find(|spec| spec.output.as_ref() == Some(format))
+        .map(|spec| spec.extensions[0])
+        .unwrap_or_default()
+}
+
+pub fn output_formats() -> Vec<OutputFormat> {
+    FORMATS.iter()
+        .filter(|spec| spec.enabled)
+        .filter_map(|spec| spec.output.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_for(name: &str) -> Option<OutputFormat> {
+        default_output(Path::new(name))
+    }
+
+    #[test]
+    fn readable_outputs_default_to_themselves() {
+        assert_eq!(default_for("a.JPG"), Some(OutputFormat::Jpeg));
+        assert_eq!(default_for("a.tif"), Some(OutputFormat::Tiff));
+        assert_eq!(default_for("a.pgm"), Some(OutputFormat::Pnm));
+    }
+
+    #[test]
+    fn input_only_formats_get_a_default_output() {
+        assert_eq!(default_for("a.heic"), Some(OutputFormat::Jpeg));
+        assert_eq!(default_for("a.svg"), Some(OutputFormat::Png));
+        assert_eq!(default_for("a.exr"), Some(OutputFormat::Png));
+        assert_eq!(default_for("a.hdr"), Some(OutputFormat::Png));
+        assert_eq!(default_for("a.dds"), Some(OutputFormat::Png));
+    }
+
+    #[test]
+    fn unknown_and_output_only_formats_are_not_inputs() {
+        assert_eq!(default_for("a.avif"), None);
+        assert_eq!(default_for("a.txt"), None);
+        assert!(!is_supported_input(Path::new("a.avif")));
+        assert!(!input_extensions().contains(&"avif"));
+    }
+}
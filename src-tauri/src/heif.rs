@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use crate::formats::{self, SourceKind};
+
+pub fn is_heif(path: &Path) -> bool {
+    formats::source_kind(path) == Some(SourceKind::Heif)
+}
+
+// libheif is a system library, so HEIF support is an optional build feature
+pub const ENABLED: bool = cfg!(feature = "heif");
+
+#[cfg(feature = "heif")]
+mod libheif {
+    use image::{DynamicImage, ImageBuffer, Rgb, Rgba};
+    use libheif_rs::{ColorSpace, HeifContext, ImageHandle, LibHeif, RgbChroma};
+    use std::path::Path;
+
+    use crate::decode::SourceHeader;
+    use crate::ProcessError;
+
+    fn open(path: &Path) -> Result<HeifContext<'static>, String> {
+        let path = path.to_str().ok_or("HEIF path is not valid UTF-8")?;
+        HeifContext::read_from_file(path)
+            .map_err(|e| format!("Failed to open HEIF image: {}", e))
+    }
+
+    fn primary_image(context: &HeifContext) -> Result<ImageHandle, String> {
+        context.primary_image_handle()
+            .map_err(|e| format!("Failed to open HEIF image: {}", e))
+    }
+
+    fn sample_bytes(handle: &ImageHandle) -> u64 {
+        if handle.luma_bits_per_pixel() > 8 { 2 } else { 1 }
+    }
+
+    // Handle dimensions already include rotation and cropping
+    pub fn read_header(path: &Path) -> Result<SourceHeader, ProcessError> {
+        let context = open(path)?;
+        let handle = primary_image(&context)?;
+        let has_alpha = handle.has_alpha_channel();
+        let channels = if has_alpha { 4 } else { 3 };
+        Ok(SourceHeader {
+            width: handle.width(),
+            height: handle.height(),
+            bytes_per_pixel: channels * sample_bytes(&handle),
+            has_alpha,
+        })
+    }
+
+    // Undo premultiplied alpha, which libheif passes through as stored
+    fn demultiply<T: Copy + Into<f32> + TryFrom<u32>>(samples: &mut [T], max: f32) {
+        for pixel in samples.chunks_exact_mut(4) {
+            let alpha: f32 = pixel[3].into();
+            if alpha <= 0.0 {
+                continue;
+            }
+            for sample in &mut pixel[..3] {
+                let value: f32 = (*sample).into();
+                let straight = (value * max / alpha).round().min(max) as u32;
+                if let Ok(straight) = T::try_from(straight) {
+                    *sample = straight;
+                }
+            }
+        }
+    }
+
+    // Decode the primary image with libheif, which applies the rotation and
+    // mirroring boxes. iPhones record orientation there rather than relying on
+    // the EXIF tag, and the EXIF copy must not be applied a second time.
+    pub fn decode(path: &Path) -> Result<DynamicImage, ProcessError> {
+        let context = open(path)?;
+        let handle = primary_image(&context)?;
+        let bits = handle.luma_bits_per_pixel();
+        let deep = bits > 8;
+        let alpha = handle.has_alpha_channel();
+        let chroma = match (deep, alpha) {
+            (false, false) => RgbChroma::Rgb,
+            (false, true) => RgbChroma::Rgba,
+            (true, false) => RgbChroma::HdrRgbLe,
+            (true, true) => RgbChroma::HdrRgbaLe,
+        };
+
+        let image = LibHeif::new()
+            .decode(&handle, ColorSpace::Rgb(chroma), None)
+            .map_err(|e| format!("Failed to decode HEIF image: {}", e))?;
+        let premultiplied = alpha && image.is_premultiplied_alpha();
+        let planes = image.planes();
+        let plane = planes.interleaved.ok_or("HEIF decoder returned no pixel data")?;
+        let (width, height) = (plane.width, plane.height);
+        let channels = if alpha { 4 } else { 3 };
+        let row_bytes = width as usize * channels * if deep { 2 } else { 1 };
+
+        // Rows may be padded; copy out exactly width × channels samples per row
+        let rows = plane.data.chunks(plane.stride).take(height as usize);
+        let invalid = || ProcessError::from("Failed to convert decoded HEIF image");
+        if deep {
+            // 10 and 12-bit samples are stretched to the full 16-bit range
+            let max = ((1u32 << bits) - 1) as f32;
+            let mut samples: Vec<u16> = rows
+                .flat_map(|row| row[..row_bytes].chunks_exact(2))
+                .map(|bytes| {
+                    let value = u16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+                    (value * 65535.0 / max).round().min(65535.0) as u16
+                })
+                .collect();
+            if premultiplied {
+                demultiply(&mut samples, 65535.0);
+            }
+            if alpha {
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, samples)
+                    .map(DynamicImage::ImageRgba16)
+                    .ok_or_else(invalid)
+            } else {
+                ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, samples)
+                    .map(DynamicImage::ImageRgb16)
+                    .ok_or_else(invalid)
+            }
+        } else {
+            let mut samples: Vec<u8> = rows
+                .flat_map(|row| row[..row_bytes].iter().copied())
+                .collect();
+            if premultiplied {
+                demultiply(&mut samples, 255.0);
+            }
+            if alpha {
+                ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, samples)
+                    .map(DynamicImage::ImageRgba8)
+                    .ok_or_else(invalid)
+            } else {
+                ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, samples)
+                    .map(DynamicImage::ImageRgb8)
+                    .ok_or_else(invalid)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "heif")]
+pub use libheif::{decode, read_header};
+
+#[cfg(not(feature = "heif"))]
+mod disabled {
+    use image::DynamicImage;
+    use std::path::Path;
+
+    use crate::decode::SourceHeader;
+    use crate::ProcessError;
+
+    const DISABLED: &str = "HEIF/HEIC support is not enabled in this build (requires the 'heif' feature)";
+
+    pub fn read_header(_path: &Path) -> Result<SourceHeader, ProcessError> {
+        Err(DISABLED.into())
+    }
+
+    pub fn decode(_path: &Path) -> Result<DynamicImage, ProcessError> {
+        Err(DISABLED.into())
+    }
+}
+
+#[cfg(not(feature = "heif"))]
+pub use disabled::{decode, read_header};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/alpha.heif")
+    }
+
+    #[test]
+    fn heif_paths_are_detected() {
+        assert!(is_heif(Path::new("IMG_0001.HEIC")));
+        assert!(is_heif(Path::new("photo.heif")));
+        assert!(!is_heif(Path::new("photo.avif")));
+    }
+
+    #[cfg(feature = "heif")]
+    #[test]
+    fn header_includes_the_alpha_channel() {
+        let header = read_header(&fixture()).unwrap();
+        assert_eq!((header.width, header.height), (256, 256));
+        assert_eq!(header.bytes_per_pixel, 4);
+        assert!(header.has_alpha);
+    }
+
+    #[cfg(feature = "heif")]
+    #[test]
+    fn decodes_to_rgba() {
+        let img = decode(&fixture()).unwrap();
+        assert_eq!(img.color(), image::ColorType::Rgba8);
+        assert_eq!((img.width(), img.height()), (256, 256));
+    }
+
+    #[cfg(not(feature = "heif"))]
+    #[test]
+    fn disabled_build_explains_the_missing_feature() {
+        let error = decode(&fixture()).unwrap_err();
+        assert!(error.message.contains("'heif' feature"));
+        assert!(read_header(&fixture()).is_err());
+    }
+}
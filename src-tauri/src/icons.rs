@@ -0,0 +1,357 @@
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use tauri::Manager;
+
+use crate::decode::{self, DecodeLimits};
+use crate::memory::MemoryBudget;
+use crate::png_encode::{self, PngOptions};
+use crate::reduce;
+use crate::resize::{self, parse_hex_color};
+use crate::svg::{self, SvgOptions};
+use crate::{ProcessError, ProcessingSettings, ResizeMode};
+
+const ICO_FILE_NAME: &str = "favicon.ico";
+const ICO_SIZES: [u32; 4] = [16, 32, 48, 256];
+// ICO entries this large are stored as PNG, smaller ones as bitmaps every reader
+// understands. Either way the entry is 32-bit RGBA, as Windows expects.
+const ICO_PNG_MIN_SIZE: u32 = 256;
+const MANIFEST_FILE_NAME: &str = "site.webmanifest";
+const HTML_FILE_NAME: &str = "favicons.html";
+const SVG_FILE_NAME: &str = "icon.svg";
+// Largest icon in the set; SVG sources are rasterized at this size
+const LARGEST_ICON: u32 = 512;
+const TRANSPARENT: &str = "#00000000";
+const DEFAULT_COLOR: &str = "#ffffff";
+
+struct PngIcon {
+    file_name: &'static str,
+    size: u32,
+    opaque: bool, // iOS fills transparency with black, so these get the background color
+}
+
+const PNG_ICONS: [PngIcon; 5] = [
+    PngIcon { file_name: "favicon-16x16.png", size: 16, opaque: false },
+    PngIcon { file_name: "favicon-32x32.png", size: 32, opaque: false },
+    PngIcon { file_name: "apple-touch-icon.png", size: 180, opaque: true },
+    PngIcon { file_name: "android-chrome-192x192.png", size: 192, opaque: false },
+    PngIcon { file_name: "android-chrome-512x512.png", size: 512, opaque: false },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconSetRequest {
+    pub path: String, // Square source image; SVG sources are rasterized at full size
+    pub output_dir: String, // The icon set is written directly into this directory, replacing earlier sets
+    pub name: Option<String>, // App name in site.webmanifest, default the source file name
+    pub short_name: Option<String>, // Home screen label, default name
+    pub theme_color: Option<String>, // Browser UI color, "#RRGGBB", default white
+    pub background_color: Option<String>, // Splash screen and apple-touch-icon background, "#RRGGBB", default white
+    pub base_path: Option<String>, // URL prefix for the files in the manifest and HTML snippet, default "/"
+    pub png: Option<PngOptions>, // PNG encoder and oxipng options
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconFile {
+    pub path: String,
+    pub width: u32, // Largest entry for favicon.ico, 0 for non-image files
+    pub height: u32,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconSetResult {
+    pub files: Vec<IconFile>,
+    pub html: String, // <head> snippet referencing the set, also written to favicons.html
+    pub warnings: Option<Vec<String>>,
+}
+
+// Fit the source into a size×size square, padding with `background`
+fn resize_icon(img: &DynamicImage, size: u32, background: &str) -> Result<DynamicImage, String> {
+    let settings = ProcessingSettings {
+        resize_mode: Some(ResizeMode::Contain),
+        max_width: Some(size),
+        max_height: Some(size),
+        background_color: Some(background.to_string()),
+        allow_enlarge: Some(true),
+        ..ProcessingSettings::default()
+    };
+    Ok(resize::resize_image(img.clone(), &settings)?.image)
+}
+
+fn encode_png(img: &DynamicImage, options: &PngOptions) -> Result<Vec<u8>, String> {
+    let analysis = reduce::analyze(img);
+    let reduced = reduce::reduce_channels(img.clone(), &analysis);
+    png_encode::encode_png_lossless(&reduced, options, Some(&analysis))
+}
+
+// 32-bit BGRA DIB as stored inside ICO files: double height header, bottom-up
+// rows, then a 1-bit AND mask marking fully transparent pixels for old readers
+fn bitmap_entry(rgba: &RgbaImage) -> Vec<u8> {
+    let (width, height) = rgba.dimensions();
+    let mask_stride = width.div_ceil(32) as usize * 4;
+    let pixel_bytes = (width * height * 4) as usize;
+    let mask_bytes = mask_stride * height as usize;
+
+    let mut data = Vec::with_capacity(40 + pixel_bytes + mask_bytes);
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&(width as i32).to_le_bytes());
+    data.extend_from_slice(&(2 * height as i32).to_le_bytes()); // Color rows plus mask rows
+    data.extend_from_slice(&1u16.to_le_bytes()); // Planes
+    data.extend_from_slice(&32u16.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes()); // No compression
+    data.extend_from_slice(&((pixel_bytes + mask_bytes) as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 16]); // Resolution and palette counts are unused
+
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            data.extend_from_slice(&[b, g, r, a]);
+        }
+    }
+    for y in (0..height).rev() {
+        let mut row = vec![0u8; mask_stride];
+        for x in 0..width {
+            if rgba.get_pixel(x, y)[3] == 0 {
+                row[(x / 8) as usize] |= 0x80 >> (x % 8);
+            }
+        }
+        data.extend_from_slice(&row);
+    }
+    data
+}
+
+// ICONDIR header, one ICONDIRENTRY per image, then the image data
+fn encode_ico(entries: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut ico = Vec::new();
+    ico.extend_from_slice(&0u16.to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes()); // Icon, not cursor
+    ico.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    let mut offset = 6 + 16 * entries.len() as u32;
+    for (size, data) in entries {
+        // 0 means 256
+        let dimension = if *size >= 256 { 0 } else { *size as u8 };
+        ico.extend_from_slice(&[dimension, dimension, 0, 0]);
+        ico.extend_from_slice(&1u16.to_le_bytes()); // Planes
+        ico.extend_from_slice(&32u16.to_le_bytes()); // Bits per pixel
+        ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&offset.to_le_bytes());
+        offset += data.len() as u32;
+    }
+    for (_, data) in entries {
+        ico.extend_from_slice(data);
+    }
+    ico
+}
+
+fn write_file(output_dir: &Path, file_name: &str, data: &[u8], dimensions: (u32, u32)) -> Result<IconFile, String> {
+    let path = output_dir.join(file_name);
+    fs::write(&path, data)
+        .map_err(|e| format!("Failed to write {}: {}", file_name, e))?;
+    Ok(IconFile {
+        path: path.to_string_lossy().to_string(),
+        width: dimensions.0,
+        height: dimensions.1,
+        size: data.len() as u64,
+    })
+}
+
+fn build_icon_set(request: &IconSetRequest, budget: &MemoryBudget) -> Result<IconSetResult, ProcessError> {
+    let input_path = Path::new(&request.path);
+    let output_dir = Path::new(&request.output_dir);
+    let png_options = request.png.clone().unwrap_or_default();
+    let theme_color = request.theme_color.as_deref().unwrap_or(DEFAULT_COLOR);
+    let background_color = request.background_color.as_deref().unwrap_or(DEFAULT_COLOR);
+    parse_hex_color(theme_color)?;
+    parse_hex_color(background_color)?;
+
+    // Same limits and memory accounting as batch processing
+    let is_svg = svg::is_svg(input_path);
+    let svg_options = SvgOptions {
+        width: Some(LARGEST_ICON),
+        height: Some(LARGEST_ICON),
+        ..SvgOptions::default()
+    };
+    let header = decode::read_header(input_path, &svg_options)?;
+    let limits = DecodeLimits::from_settings(&ProcessingSettings::default());
+    decode::check_limits(&header, &limits)?;
+    let _reservation = budget.reserve(MemoryBudget::estimate(&header))?;
+    let img = if is_svg {
+        svg::rasterize(input_path, &svg_options)?
+    } else {
+        decode::load_image(input_path, None, &limits)?
+    };
+
+    let mut warnings = Vec::new();
+    let (width, height) = img.dimensions();
+    if width != height {
+        warnings.push(format!(
+            "Source is {}×{}, not square; icons are padded with transparency",
+            width, height
+        ));
+    }
+    if !is_svg && width.max(height) < LARGEST_ICON {
+        warnings.push(format!(
+            "Source is smaller than {}×{}; larger icons are upscaled and may look soft",
+            LARGEST_ICON, LARGEST_ICON
+        ));
+    }
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let mut files = Vec::new();
+
+    let mut ico_entries = Vec::new();
+    for size in ICO_SIZES {
+        let icon = resize_icon(&img, size, TRANSPARENT)?;
+        let data = if size >= ICO_PNG_MIN_SIZE {
+            png_encode::encode_png_rgba(&icon.to_rgba8(), &png_options)?
+        } else {
+            bitmap_entry(&icon.to_rgba8())
+        };
+        ico_entries.push((size, data));
+    }
+    let largest = ICO_SIZES[ICO_SIZES.len() - 1];
+    files.push(write_file(output_dir, ICO_FILE_NAME, &encode_ico(&ico_entries), (largest, largest))?);
+
+    for spec in &PNG_ICONS {
+        let background = if spec.opaque { background_color } else { TRANSPARENT };
+        let icon = resize_icon(&img, spec.size, background)?;
+        let data = encode_png(&icon, &png_options)?;
+        files.push(write_file(output_dir, spec.file_name, &data, (spec.size, spec.size))?);
+    }
+
+    // Browsers that support SVG favicons get the original, which stays sharp at any size
+    if is_svg {
+        let data = fs::read(input_path)
+            .map_err(|e| format!("Failed to read SVG: {}", e))?;
+        files.push(write_file(output_dir, SVG_FILE_NAME, &data, (0, 0))?);
+    }
+
+    let base_path = match request.base_path.as_deref() {
+        Some(base) if base.ends_with('/') => base.to_string(),
+        Some(base) => format!("{}/", base),
+        None => "/".to_string(),
+    };
+    let name = request.name.clone().unwrap_or_else(|| {
+        input_path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    let manifest_icons: Vec<_> = PNG_ICONS.iter()
+        .filter(|spec| spec.file_name.starts_with("android-chrome"))
+        .map(|spec| json!({
+            "src": format!("{}{}", base_path, spec.file_name),
+            "sizes": format!("{}x{}", spec.size, spec.size),
+            "type": "image/png",
+        }))
+        .collect();
+    let manifest = json!({
+        "name": name,
+        "short_name": request.short_name.clone().unwrap_or_else(|| name.clone()),
+        "icons": manifest_icons,
+        "theme_color": theme_color,
+        "background_color": background_color,
+        "display": "standalone",
+    });
+    let manifest = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    files.push(write_file(output_dir, MANIFEST_FILE_NAME, manifest.as_bytes(), (0, 0))?);
+
+    let mut html = format!("<link rel=\"icon\" href=\"{}{}\" sizes=\"any\">\n", base_path, ICO_FILE_NAME);
+    if is_svg {
+        html.push_str(&format!("<link rel=\"icon\" href=\"{}{}\" type=\"image/svg+xml\">\n", base_path, SVG_FILE_NAME));
+    }
+    html.push_str(&format!(
+        "<link rel=\"icon\" type=\"image/png\" sizes=\"32x32\" href=\"{0}favicon-32x32.png\">\n\
+         <link rel=\"icon\" type=\"image/png\" sizes=\"16x16\" href=\"{0}favicon-16x16.png\">\n\
+         <link rel=\"apple-touch-icon\" sizes=\"180x180\" href=\"{0}apple-touch-icon.png\">\n\
+         <link rel=\"manifest\" href=\"{0}{1}\">\n\
+         <meta name=\"theme-color\" content=\"{2}\">\n",
+        base_path, MANIFEST_FILE_NAME, theme_color
+    ));
+    files.push(write_file(output_dir, HTML_FILE_NAME, html.as_bytes(), (0, 0))?);
+
+    Ok(IconSetResult {
+        files,
+        html,
+        warnings: (!warnings.is_empty()).then_some(warnings),
+    })
+}
+
+// Favicon and app icon set from one square source: multi-size favicon.ico,
+// PNG favicons, Apple touch icon, Android/PWA icons, manifest and HTML snippet
+#[tauri::command]
+pub async fn generate_icon_set(request: IconSetRequest, app: tauri::AppHandle) -> Result<IconSetResult, String> {
+    tokio::task::spawn_blocking(move || {
+        let budget = app.state::<MemoryBudget>();
+        build_icon_set(&request, &budget).map_err(|e| e.message)
+    })
+    .await
+    .map_err(|e| format!("Failed to generate icon set: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::ico::IcoDecoder;
+    use std::io::Cursor;
+
+    // Gradient with a fully transparent corner and partial alpha elsewhere
+    fn icon(size: u32) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| {
+            let alpha = if x < size / 4 && y < size / 4 { 0 } else { (128 + x * 127 / size) as u8 };
+            image::Rgba([(x * 255 / size) as u8, (y * 255 / size) as u8, 90, alpha])
+        })
+    }
+
+    fn decode_ico(data: &[u8]) -> RgbaImage {
+        let decoder = IcoDecoder::new(Cursor::new(data)).unwrap();
+        DynamicImage::from_decoder(decoder).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn ico_entries_round_trip() {
+        for size in [16, 32, 48] {
+            let source = icon(size);
+            let decoded = decode_ico(&encode_ico(&[(size, bitmap_entry(&source))]));
+            // Readers drop the color of fully transparent pixels
+            for (decoded, source) in decoded.pixels().zip(source.pixels()) {
+                if source[3] == 0 {
+                    assert_eq!(decoded[3], 0);
+                } else {
+                    assert_eq!(decoded, source, "{}×{} entry", size, size);
+                }
+            }
+        }
+
+        let source = icon(256);
+        let png = png_encode::encode_png_rgba(&source, &PngOptions::default()).unwrap();
+        // IHDR bit depth 8, color type 6 (RGBA)
+        assert_eq!(&png[24..26], &[8, 6]);
+        assert_eq!(decode_ico(&encode_ico(&[(256, png)])), source);
+    }
+
+    #[test]
+    fn ico_directory_lists_every_entry() {
+        let entries: Vec<(u32, Vec<u8>)> = [16, 32, 48]
+            .into_iter()
+            .map(|size| (size, bitmap_entry(&icon(size))))
+            .collect();
+        let ico = encode_ico(&entries);
+        assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), 3);
+
+        for (i, (size, data)) in entries.iter().enumerate() {
+            let entry = &ico[6 + 16 * i..6 + 16 * (i + 1)];
+            assert_eq!(entry[0] as u32, *size);
+            assert_eq!(u16::from_le_bytes([entry[6], entry[7]]), 32);
+            let length = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as usize;
+            let offset = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as usize;
+            assert_eq!(&ico[offset..offset + length], &data[..]);
+        }
+        // The decoder picks the largest entry
+        assert_eq!(decode_ico(&ico).dimensions(), (48, 48));
+    }
+}
@@ -0,0 +1,516 @@
+use image::{DynamicImage, GenericImageView};
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+use mozjpeg::ColorSpace;
+use mozjpeg_sys as ffi;
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use std::fs;
+use std::mem;
+use std::os::raw::{c_int, c_uint, c_ulong};
+use std::path::Path;
+use std::ptr;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum JpegBackend {
+    #[default]
+    #[serde(rename = "standard")]
+    Standard, // Pure Rust encoder, fast
+    #[serde(rename = "mozjpeg")]
+    Mozjpeg, // Trellis quantization and perceptually tuned tables, noticeably smaller files but slower
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ChromaSubsampling {
+    #[serde(rename = "444")]
+    Full, // No subsampling, best for text and sharp color edges
+    #[serde(rename = "422")]
+    Half, // Chroma halved horizontally
+    #[serde(rename = "420")]
+    Quarter, // Chroma halved in both directions, smallest files
+}
+
+impl ChromaSubsampling {
+    fn sampling_factor(self) -> SamplingFactor {
+        match self {
+            ChromaSubsampling::Full => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Half => SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Quarter => SamplingFactor::R_4_2_0,
+        }
+    }
+
+    // Size of one chroma sample in luma pixels, as mozjpeg expects it
+    fn chroma_pixel_size(self) -> (u8, u8) {
+        match self {
+            ChromaSubsampling::Full => (1, 1),
+            ChromaSubsampling::Half => (2, 1),
+            ChromaSubsampling::Quarter => (2, 2),
+        }
+    }
+}
+
+// Metadata dropped by lossless optimization
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum MetadataStrip {
+    #[serde(rename = "none")]
+    None, // Keep all markers
+    #[default]
+    #[serde(rename = "safe")]
+    Safe, // Keep EXIF and the ICC profile, drop comments, XMP and application data
+    #[serde(rename = "all")]
+    All, // Keep no metadata at all
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JpegOptions {
+    pub encoder: Option<JpegBackend>, // Default standard
+    pub progressive: Option<bool>, // Progressive scans, default false for standard, true for mozjpeg
+    pub chroma_subsampling: Option<ChromaSubsampling>, // Default 420 (standard: 444 from quality 90)
+    pub optimize_huffman: Option<bool>, // Per-image Huffman tables, default true
+    pub restart_interval: Option<u16>, // MCUs between restart markers, standard encoder only (an error with mozjpeg)
+    pub lossless: Option<bool>, // Optimize mode: rewrite JPEG sources without re-encoding (like jpegtran), default false
+    pub strip_metadata: Option<MetadataStrip>, // Lossless mode only, default safe
+}
+
+fn encode_standard(img: &DynamicImage, quality: u8, options: &JpegOptions) -> Result<Vec<u8>, String> {
+    let (width, height) = img.dimensions();
+    let mut data = Vec::new();
+    let mut encoder = Encoder::new(&mut data, quality);
+
+    encoder.set_progressive(options.progressive.unwrap_or(false));
+    encoder.set_optimized_huffman_tables(options.optimize_huffman.unwrap_or(true));
+    if let Some(subsampling) = options.chroma_subsampling {
+        encoder.set_sampling_factor(subsampling.sampling_factor());
+    }
+    if let Some(interval) = options.restart_interval.filter(|&interval| interval > 0) {
+        encoder.set_restart_interval(interval);
+    }
+
+    // Grayscale stays single-channel; everything else is encoded as RGB
+    let result = match img {
+        DynamicImage::ImageLuma8(gray) => {
+            encoder.encode(gray.as_raw(), width as u16, height as u16, ColorType::Luma)
+        }
+        _ => {
+            let rgb = img.to_rgb8();
+            encoder.encode(rgb.as_raw(), width as u16, height as u16, ColorType::Rgb)
+        }
+    };
+    result.map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+    Ok(data)
+}
+
+fn encode_mozjpeg(img: &DynamicImage, quality: u8, options: &JpegOptions) -> Result<Vec<u8>, String> {
+    // The mozjpeg bindings have no way to set it, and dropping it silently
+    // would write a file without the markers that were asked for
+    if options.restart_interval.is_some_and(|interval| interval > 0) {
+        return Err("Restart intervals are only supported by the standard JPEG encoder".to_string());
+    }
+
+    let (width, height) = img.dimensions();
+    let (pixels, color_space) = match img {
+        DynamicImage::ImageLuma8(gray) => (gray.as_raw().clone(), ColorSpace::JCS_GRAYSCALE),
+        _ => (img.to_rgb8().into_raw(), ColorSpace::JCS_RGB),
+    };
+
+    // libjpeg reports errors by unwinding, so they have to be caught here
+    let result = std::panic::catch_unwind(|| -> std::io::Result<Vec<u8>> {
+        let mut compress = mozjpeg::Compress::new(color_space);
+        compress.set_size(width as usize, height as usize);
+        compress.set_quality(quality as f32);
+        compress.set_optimize_coding(options.optimize_huffman.unwrap_or(true));
+
+        // The default profile installs a progressive scan script. Turning scan
+        // optimization off also clears that script, and with no script libjpeg
+        // writes a single baseline scan.
+        if !options.progressive.unwrap_or(true) {
+            compress.set_optimize_scans(false);
+        }
+        if let (Some(subsampling), ColorSpace::JCS_RGB) = (options.chroma_subsampling, color_space) {
+            let size = subsampling.chroma_pixel_size();
+            compress.set_chroma_sampling_pixel_sizes(size, size);
+        }
+
+        let mut started = compress.start_compress(Vec::new())?;
+        started.write_scanlines(&pixels)?;
+        started.finish()
+    });
+
+    result
+        .map_err(|_| "Failed to encode JPEG: mozjpeg aborted".to_string())?
+        .map_err(|e| format!("Failed to encode JPEG: {}", e))
+}
+
+pub fn encode_jpeg(img: &DynamicImage, quality: u8, options: &JpegOptions) -> Result<Vec<u8>, String> {
+    let (width, height) = img.dimensions();
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(format!(
+            "Image is {}×{}, JPEG supports at most {}×{}",
+            width, height, u16::MAX, u16::MAX
+        ));
+    }
+
+    let quality = quality.clamp(1, 100);
+    match options.encoder.unwrap_or_default() {
+        JpegBackend::Standard => encode_standard(img, quality, options),
+        JpegBackend::Mozjpeg => encode_mozjpeg(img, quality, options),
+    }
+}
+
+pub fn save_jpeg(
+    img: &DynamicImage,
+    output_path: &Path,
+    quality: u8,
+    options: &JpegOptions,
+) -> Result<(), String> {
+    let data = encode_jpeg(img, quality, options)?;
+    fs::write(output_path, data)
+        .map_err(|e| format!("Failed to write JPEG file: {}", e))
+}
+
+const APP1: c_int = 0xE1;
+const APP2: c_int = 0xE2;
+
+extern "C" {
+    // Buffers from jpeg_mem_dest are allocated by libjpeg with malloc
+    fn free(ptr: *mut c_void);
+}
+
+// libjpeg cannot return errors, so its error handler unwinds with the message
+extern "C-unwind" fn unwind_error_exit(cinfo: &mut ffi::jpeg_common_struct) {
+    let message = unsafe {
+        let err = &*cinfo.err;
+        match err.format_message {
+            Some(format) => {
+                let mut buffer = [0u8; 80];
+                // The binding declares the buffer immutable, but libjpeg writes to it
+                let format = mem::transmute::<
+                    unsafe extern "C-unwind" fn(&mut ffi::jpeg_common_struct, &[u8; 80]),
+                    unsafe extern "C-unwind" fn(&mut ffi::jpeg_common_struct, &mut [u8; 80]),
+                >(format);
+                format(cinfo, &mut buffer);
+                let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+                String::from_utf8_lossy(&buffer[..len]).to_string()
+            }
+            None => format!("libjpeg error {}", err.msg_code),
+        }
+    };
+    std::panic::resume_unwind(Box::new(message));
+}
+
+extern "C-unwind" fn silence_message(_cinfo: &mut ffi::jpeg_common_struct, _level: c_int) {}
+
+// Owns both libjpeg objects so they are released even when libjpeg unwinds.
+// Boxed because libjpeg keeps pointers into them.
+struct Transcoder {
+    err: Box<ffi::jpeg_error_mgr>,
+    src: Box<ffi::jpeg_decompress_struct>,
+    dst: Box<ffi::jpeg_compress_struct>,
+    out_buffer: Box<*mut u8>,
+    out_size: Box<c_ulong>,
+}
+
+impl Transcoder {
+    unsafe fn new() -> Self {
+        let mut transcoder = Transcoder {
+            err: Box::new(mem::zeroed()),
+            src: Box::new(mem::zeroed()),
+            dst: Box::new(mem::zeroed()),
+            out_buffer: Box::new(ptr::null_mut()),
+            out_size: Box::new(0),
+        };
+        ffi::jpeg_std_error(&mut transcoder.err);
+        transcoder.err.error_exit = Some(unwind_error_exit);
+        transcoder.err.emit_message = Some(silence_message);
+
+        let err: *mut ffi::jpeg_error_mgr = &mut *transcoder.err;
+        transcoder.src.common.err = err;
+        transcoder.dst.common.err = err;
+        ffi::jpeg_create_decompress(&mut *transcoder.src);
+        ffi::jpeg_create_compress(&mut *transcoder.dst);
+        transcoder
+    }
+}
+
+impl Drop for Transcoder {
+    fn drop(&mut self) {
+        unsafe {
+            // The coefficient arrays belong to the source, so it goes last
+            ffi::jpeg_destroy_compress(&mut self.dst);
+            ffi::jpeg_destroy_decompress(&mut self.src);
+            if !self.out_buffer.is_null() {
+                free(*self.out_buffer as *mut c_void);
+            }
+        }
+    }
+}
+
+// The compressor writes its own JFIF and Adobe markers
+fn is_regenerated(marker: &ffi::jpeg_marker_struct, data: &[u8]) -> bool {
+    let marker_code = marker.marker as c_int;
+    (marker_code == ffi::jpeg_marker::APP0 as c_int && data.starts_with(b"JFIF\0"))
+        || (marker_code == ffi::jpeg_marker::APP0 as c_int + 14 && data.starts_with(b"Adobe"))
+}
+
+fn keep_marker(marker: &ffi::jpeg_marker_struct, data: &[u8], strip: MetadataStrip) -> bool {
+    let marker_code = marker.marker as c_int;
+    match strip {
+        MetadataStrip::None => true,
+        MetadataStrip::Safe => {
+            (marker_code == APP1 && data.starts_with(b"Exif\0\0"))
+                || (marker_code == APP2 && data.starts_with(b"ICC_PROFILE\0"))
+        }
+        MetadataStrip::All => false,
+    }
+}
+
+// Returns the transcoded data and whether any metadata was stripped
+unsafe fn transcode(data: &[u8], options: &JpegOptions) -> (Vec<u8>, bool) {
+    let strip = options.strip_metadata.unwrap_or_default();
+    let mut t = Transcoder::new();
+
+    // Every marker is read, even those that are dropped, to know if any were
+    ffi::jpeg_mem_src(&mut t.src, data.as_ptr(), data.len() as c_ulong);
+    ffi::jpeg_save_markers(&mut t.src, ffi::jpeg_marker::COM as c_int, 0xFFFF);
+    for app in 0..16 {
+        ffi::jpeg_save_markers(&mut t.src, ffi::jpeg_marker::APP0 as c_int + app, 0xFFFF);
+    }
+    ffi::jpeg_read_header(&mut t.src, 1);
+    let coefficients = ffi::jpeg_read_coefficients(&mut t.src);
+
+    // Same quantization and sampling as the source, so the coefficients are
+    // reused exactly; only the entropy coding changes
+    ffi::jpeg_copy_critical_parameters(&t.src, &mut t.dst);
+    t.dst.optimize_coding = options.optimize_huffman.unwrap_or(true) as ffi::boolean;
+    if options.progressive.unwrap_or(true) {
+        ffi::jpeg_simple_progression(&mut t.dst);
+    } else {
+        ffi::jpeg_c_set_bool_param(&mut t.dst, ffi::J_BOOLEAN_PARAM::JBOOLEAN_OPTIMIZE_SCANS, 0);
+        t.dst.scan_info = ptr::null();
+        t.dst.num_scans = 0;
+    }
+
+    let out_buffer: *mut *mut u8 = &mut *t.out_buffer;
+    let out_size: *mut c_ulong = &mut *t.out_size;
+    ffi::jpeg_mem_dest(&mut t.dst, out_buffer, out_size);
+    ffi::jpeg_write_coefficients(&mut t.dst, coefficients);
+
+    let mut stripped = false;
+    let mut marker = t.src.marker_list;
+    while let Some(saved) = marker.as_ref() {
+        let marker_data = std::slice::from_raw_parts(saved.data, saved.data_length as usize);
+        if is_regenerated(saved, marker_data) {
+            // Written again by the compressor
+        } else if keep_marker(saved, marker_data, strip) {
+            ffi::jpeg_write_marker(&mut t.dst, saved.marker as c_int, saved.data, saved.data_length as c_uint);
+        } else {
+            stripped = true;
+        }
+        marker = saved.next;
+    }
+
+    ffi::jpeg_finish_compress(&mut t.dst);
+    ffi::jpeg_finish_decompress(&mut t.src);
+
+    (std::slice::from_raw_parts(*t.out_buffer, *t.out_size as usize).to_vec(), stripped)
+}
+
+// Rewrite a JPEG from its DCT coefficients, like jpegtran: Huffman tables are
+// rebuilt, scans made progressive and metadata stripped, while the decoded
+// pixels stay bit-identical. Sources that are already smaller are kept as is,
+// unless they carry metadata that was asked to be stripped.
+pub fn optimize_lossless(data: &[u8], options: &JpegOptions) -> Result<Vec<u8>, String> {
+    let (optimized, stripped) = std::panic::catch_unwind(|| unsafe { transcode(data, options) }).map_err(|payload| {
+        let message = payload.downcast::<String>()
+            .map(|message| *message)
+            .unwrap_or_else(|_| "libjpeg aborted".to_string());
+        format!("Failed to optimize JPEG: {}", message)
+    })?;
+
+    if optimized.len() >= data.len() && !stripped {
+        return Ok(data.to_vec());
+    }
+    Ok(optimized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageFormat;
+
+    // Like a small photo
+    fn fixture() -> DynamicImage {
+        crate::bench_common::synthetic_photo(256, 192)
+    }
+
+    fn options(encoder: JpegBackend, progressive: Option<bool>) -> JpegOptions {
+        JpegOptions {
+            encoder: Some(encoder),
+            progressive,
+            ..Default::default()
+        }
+    }
+
+    // Code of the start-of-frame marker: 0xC0 baseline, 0xC2 progressive
+    fn frame_marker(data: &[u8]) -> Option<u8> {
+        let mut pos = 2;
+        while pos + 4 <= data.len() && data[pos] == 0xFF {
+            let marker = data[pos + 1];
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                return Some(marker);
+            }
+            pos += 2 + u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        }
+        None
+    }
+
+    #[test]
+    fn mozjpeg_is_smaller_than_standard() {
+        let img = fixture();
+        for quality in [60, 75, 85] {
+            let standard = encode_jpeg(&img, quality, &options(JpegBackend::Standard, None)).unwrap();
+            let mozjpeg = encode_jpeg(&img, quality, &options(JpegBackend::Mozjpeg, None)).unwrap();
+            assert!(
+                mozjpeg.len() < standard.len(),
+                "quality {}: mozjpeg {} bytes, standard {} bytes",
+                quality,
+                mozjpeg.len(),
+                standard.len(),
+            );
+        }
+    }
+
+    #[test]
+    fn progressive_off_writes_baseline() {
+        for encoder in [JpegBackend::Standard, JpegBackend::Mozjpeg] {
+            let data = encode_jpeg(&fixture(), 75, &options(encoder, Some(false))).unwrap();
+            assert_eq!(frame_marker(&data), Some(0xC0), "{:?}", encoder);
+        }
+        let data = encode_jpeg(&fixture(), 75, &options(JpegBackend::Mozjpeg, None)).unwrap();
+        assert_eq!(frame_marker(&data), Some(0xC2));
+    }
+
+    fn decode(data: &[u8]) -> Vec<u8> {
+        image::load_from_memory_with_format(data, ImageFormat::Jpeg).unwrap().into_bytes()
+    }
+
+    // Baseline without optimized Huffman tables, which lossless mode can shrink
+    fn unoptimized_source() -> Vec<u8> {
+        let options = JpegOptions {
+            progressive: Some(false),
+            optimize_huffman: Some(false),
+            ..Default::default()
+        };
+        encode_jpeg(&fixture(), 80, &options).unwrap()
+    }
+
+    // Insert a marker segment right after SOI
+    fn with_segment(data: &[u8], marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = data[..2].to_vec();
+        out.extend_from_slice(&[0xFF, marker]);
+        out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&data[2..]);
+        out
+    }
+
+    fn contains(data: &[u8], needle: &[u8]) -> bool {
+        data.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn mozjpeg_rejects_restart_intervals() {
+        for (encoder, supported) in [(JpegBackend::Standard, true), (JpegBackend::Mozjpeg, false)] {
+            let with_restarts = JpegOptions { restart_interval: Some(4), ..options(encoder, None) };
+            assert_eq!(encode_jpeg(&fixture(), 75, &with_restarts).is_ok(), supported);
+        }
+    }
+
+    #[test]
+    fn lossless_keeps_pixels_identical() {
+        let source = unoptimized_source();
+        for progressive in [true, false] {
+            let options = JpegOptions {
+                progressive: Some(progressive),
+                ..Default::default()
+            };
+            let optimized = optimize_lossless(&source, &options).unwrap();
+            assert!(optimized.len() < source.len());
+            assert_eq!(frame_marker(&optimized), Some(if progressive { 0xC2 } else { 0xC0 }));
+            assert_eq!(decode(&optimized), decode(&source));
+        }
+    }
+
+    #[test]
+    fn lossless_keeps_exif_in_safe_mode() {
+        let exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0";
+        let source = with_segment(&with_segment(&unoptimized_source(), 0xFE, b"a comment"), 0xE1, exif);
+
+        let safe = optimize_lossless(&source, &JpegOptions::default()).unwrap();
+        assert!(contains(&safe, exif));
+        assert!(!contains(&safe, b"a comment"));
+
+        let options = JpegOptions {
+            strip_metadata: Some(MetadataStrip::All),
+            ..Default::default()
+        };
+        let stripped = optimize_lossless(&source, &options).unwrap();
+        assert!(!contains(&stripped, b"Exif\0\0"));
+    }
+
+    // Whether a marker segment appears before the first scan
+    fn has_segment(data: &[u8], marker: u8) -> bool {
+        let mut pos = 2;
+        while pos + 4 <= data.len() && data[pos] == 0xFF && data[pos + 1] != 0xDA {
+            if data[pos + 1] == marker {
+                return true;
+            }
+            pos += 2 + u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        }
+        false
+    }
+
+    #[test]
+    fn lossless_strips_metadata_even_when_the_result_is_larger() {
+        // Already optimized and progressive, rewritten as plain baseline
+        let smallest = optimize_lossless(&unoptimized_source(), &JpegOptions::default()).unwrap();
+        let source = with_segment(&smallest, 0xE1, b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0");
+        let options = JpegOptions {
+            progressive: Some(false),
+            optimize_huffman: Some(false),
+            strip_metadata: Some(MetadataStrip::All),
+            ..Default::default()
+        };
+
+        let stripped = optimize_lossless(&source, &options).unwrap();
+        assert!(stripped.len() > smallest.len());
+        assert!(has_segment(&source, 0xE1));
+        assert!(!has_segment(&stripped, 0xE1));
+        assert_eq!(decode(&stripped), decode(&source));
+    }
+
+    #[test]
+    fn lossless_keeps_the_source_when_nothing_was_stripped() {
+        let smallest = optimize_lossless(&unoptimized_source(), &JpegOptions::default()).unwrap();
+        let options = JpegOptions {
+            progressive: Some(false),
+            optimize_huffman: Some(false),
+            strip_metadata: Some(MetadataStrip::All),
+            ..Default::default()
+        };
+        assert_eq!(optimize_lossless(&smallest, &options).unwrap(), smallest);
+    }
+
+    #[test]
+    fn lossless_rejects_corrupt_input() {
+        assert!(optimize_lossless(b"not a jpeg at all", &JpegOptions::default()).is_err());
+        let source = unoptimized_source();
+        assert!(optimize_lossless(&source[..40], &JpegOptions::default()).is_err());
+    }
+
+    #[test]
+    fn lossless_never_grows_the_file() {
+        let optimized = optimize_lossless(&unoptimized_source(), &JpegOptions::default()).unwrap();
+        let again = optimize_lossless(&optimized, &JpegOptions::default()).unwrap();
+        assert!(again.len() <= optimized.len());
+    }
+}
@@ -1,16 +1,64 @@
-use image::{DynamicImage, ImageFormat, GenericImageView};
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+use image::{ColorType, DynamicImage, ImageFormat, GenericImageView};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::BufWriter;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::{Emitter, State, Manager};
 
+mod bmp_encode;
+mod avif_encode;
+mod cache;
+pub mod decode;
+mod depth;
+mod formats;
+mod heif;
+mod icons;
+pub mod jpeg;
+mod matte;
+mod memory;
+mod png_encode;
+mod presets;
+mod reduce;
+pub mod resize;
+mod rules;
+mod scan;
+mod smartcrop;
+mod svg;
+mod tonemap;
+mod watch;
+mod webp_encode;
+
+// Synthetic test images, shared with the benches
+#[cfg(test)]
+#[path = "../benches/common/mod.rs"]
+mod bench_common;
+
+use cache::{CacheEntry, ProcessingCache};
+use decode::DecodeLimits;
+use depth::SampleDepth;
+use jpeg::JpegOptions;
+use memory::MemoryBudget;
+use png_encode::PngOptions;
+use presets::PresetStore;
+use resize::{ResizeBackend, ResizeDecision, ResizeFilter, SharpenOptions};
+use rules::{SettingsPatch, SettingsRule};
+use scan::ScanCancellationFlag;
+use smartcrop::FocalPoint;
+use svg::SvgOptions;
+use tonemap::ToneMapOptions;
+use watch::WatchRegistry;
+use webp_encode::WebpOptions;
+
 // Global cancellation flag
 pub struct CancellationFlag(Arc<AtomicBool>);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum OperationMode {
+    #[default]
     #[serde(rename = "optimize")]
     Optimize, // Only compress (with quality)
     #[serde(rename = "resize")]
@@ -29,9 +77,21 @@ pub enum ResizeMode {
     Dimensions, // Resize by specific dimensions
     #[serde(rename = "percentage")]
     Percentage, // Resize by percentage
+    #[serde(rename = "cover")]
+    Cover, // Scale to cover max_width x max_height, then center crop
+    #[serde(rename = "contain")]
+    Contain, // Scale to fit max_width x max_height, then pad with background_color
+    #[serde(rename = "long_edge")]
+    LongEdge, // Longest side becomes resize_length
+    #[serde(rename = "short_edge")]
+    ShortEdge, // Shortest side becomes resize_length
+    #[serde(rename = "width")]
+    Width, // Constrain width to max_width, height follows
+    #[serde(rename = "height")]
+    Height, // Constrain height to max_height, width follows
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OutputFormat {
     #[serde(rename = "png")]
     Png,
@@ -45,20 +105,36 @@ pub enum OutputFormat {
     Qoi,
     #[serde(rename = "bmp")]
     Bmp,
+    #[serde(rename = "tga")]
+    Tga,
+    #[serde(rename = "pnm")]
+    Pnm, // PGM/PPM for 8-bit gray/RGB, PAM otherwise
+    #[serde(rename = "ico")]
+    Ico, // Single image up to 256×256
+    #[serde(rename = "gif")]
+    Gif, // Static, palette quantized
+    #[serde(rename = "avif")]
+    Avif, // Lossy AV1, 8-bit
 }
 
 impl OutputFormat {
     fn extension(&self) -> &str {
+        formats::output_extension(self)
+    }
+
+    // Deepest samples the encoder can store without losing precision
+    fn max_sample_depth(&self) -> SampleDepth {
         match self {
-            OutputFormat::Png => "png",
-            OutputFormat::Webp => "webp",
-            OutputFormat::Jpeg => "jpg",
-            OutputFormat::Tiff => "tiff",
-            OutputFormat::Qoi => "qoi",
-            OutputFormat::Bmp => "bmp",
+            OutputFormat::Tiff => SampleDepth::Float,
+            OutputFormat::Png | OutputFormat::Pnm => SampleDepth::Sixteen,
+            _ => SampleDepth::Eight,
         }
     }
 
+    fn supports_alpha(&self) -> bool {
+        !matches!(self, OutputFormat::Jpeg)
+    }
+
     fn to_image_format(&self) -> Option<ImageFormat> {
         match self {
             OutputFormat::Png => Some(ImageFormat::Png),
@@ -67,37 +143,67 @@ impl OutputFormat {
             OutputFormat::Bmp => Some(ImageFormat::Bmp),
             OutputFormat::Qoi => Some(ImageFormat::Qoi),
             OutputFormat::Webp => Some(ImageFormat::WebP),
+            OutputFormat::Tga => Some(ImageFormat::Tga),
+            OutputFormat::Pnm => Some(ImageFormat::Pnm),
+            OutputFormat::Ico => Some(ImageFormat::Ico),
+            OutputFormat::Gif => Some(ImageFormat::Gif),
+            OutputFormat::Avif => Some(ImageFormat::Avif),
         }
     }
 
     fn from_path(path: &Path) -> Option<OutputFormat> {
-        let ext = path.extension()?.to_str()?.to_lowercase();
-        match ext.as_str() {
-            "png" => Some(OutputFormat::Png),
-            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
-            "webp" => Some(OutputFormat::Webp),
-            "tiff" | "tif" => Some(OutputFormat::Tiff),
-            "qoi" => Some(OutputFormat::Qoi),
-            "bmp" => Some(OutputFormat::Bmp),
-            _ => None,
-        }
+        formats::output_for_path(path)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OptimizeBatchRequest {
-    pub paths: Vec<String>,
-    pub output_dir: String,
-    pub format: Option<OutputFormat>, // Only for 'convert' mode, otherwise use original format
-    pub overwrite: bool,
+// Everything that decides how a file is processed, independent of which files
+// are processed and where they go. Flattened into requests so the wire format
+// stays a single flat object.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessingSettings {
+    pub format: Option<OutputFormat>, // Default: the source format, JPEG for HEIF, PNG for SVG, EXR, HDR and DDS
     pub operation_mode: OperationMode, // Operation mode
-    pub quality: Option<f32>, // 0.0 - 100.0, default 75 for WebP, 80 for JPEG
+    pub quality: Option<f32>, // 0.0 - 100.0, default 75 for WebP, 80 for JPEG, 70 for AVIF
     pub resize_mode: Option<ResizeMode>, // Resize mode: dimensions or percentage
     pub resize_percentage: Option<f32>, // 1.0 - 100.0, percentage to resize
     pub max_width: Option<u32>, // Optional resize width (when resize_mode = dimensions)
     pub max_height: Option<u32>, // Optional resize height (when resize_mode = dimensions)
     pub keep_aspect_ratio: Option<bool>, // Keep aspect ratio when resizing, default true
+    pub resize_length: Option<u32>, // Edge length for long_edge / short_edge modes
+    pub background_color: Option<String>, // Padding color for contain mode, "#RRGGBB[AA]", default white
+    pub alpha_matte: Option<String>, // Background for transparency in formats without alpha: "#RRGGBB", "checker" or "auto", default white
+    pub allow_enlarge: Option<bool>, // Allow upscaling (percentages above 100, small images to the target), default false
+    pub min_output_width: Option<u32>, // Aspect-preserving resizes never go below this width
+    pub min_output_height: Option<u32>, // Aspect-preserving resizes never go below this height
+    pub skip_below_width: Option<u32>, // Leave images narrower than this unresized
+    pub skip_below_height: Option<u32>, // Leave images shorter than this unresized
+    pub resize_filter: Option<ResizeFilter>, // Resampling filter, default lanczos3
+    pub resize_backend: Option<ResizeBackend>, // Resampling implementation, default standard
+    pub sharpen: Option<SharpenOptions>, // Unsharp mask applied after downscaling
+    pub jpeg: Option<JpegOptions>, // JPEG encoder options
+    pub webp: Option<WebpOptions>, // WebP encoder options
+    pub png: Option<PngOptions>, // PNG encoder and oxipng options
+    pub svg: Option<SvgOptions>, // Raster size and background for SVG sources
+    pub tone_map: Option<ToneMapOptions>, // How EXR and Radiance HDR sources are brought into display range
+    pub preserve_bit_depth: Option<bool>, // Keep 16-bit and float samples when the output format can store them, default true
+    pub reduce_color_type: Option<bool>, // Write PNG, TIFF, WebP and BMP in their smallest lossless layout (grayscale, no alpha, small palettes), default true
+    pub smart_crop: Option<bool>, // Pick the cover crop window by saliency instead of centering, default false
+    pub focal_point: Option<FocalPoint>, // Explicit cover crop center, e.g. one returned in a previous FileResult
+    pub max_pixels: Option<u64>, // Refuse images with more pixels than this, default 250 million
+    pub max_decode_bytes: Option<u64>, // Refuse images whose decoded pixels need more memory than this, default 512 MiB
+    pub rules: Option<Vec<SettingsRule>>, // Conditional overrides resolved per file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizeBatchRequest {
+    pub paths: Vec<String>,
+    pub output_dir: String,
+    pub overwrite: bool,
+    #[serde(flatten)]
+    pub settings: ProcessingSettings,
     pub create_backup: Option<bool>, // Create backup before overwriting, default true when overwrite is true
+    pub use_cache: Option<bool>, // Skip files whose output is already up-to-date, default true
+    pub overrides: Option<HashMap<String, SettingsPatch>>, // Per-path overrides, applied after rules
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +222,45 @@ pub enum FileStatus {
     Success,
     #[serde(rename = "failed")]
     Failed,
+    #[serde(rename = "cached")]
+    Cached, // Output already up-to-date, nothing was written
+}
+
+// Machine-readable reason for a failure, for errors the UI handles specially
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ErrorCode {
+    #[serde(rename = "image_too_large")]
+    ImageTooLarge, // More pixels than max_pixels
+    #[serde(rename = "decode_limit_exceeded")]
+    DecodeLimitExceeded, // Decoding would allocate more than max_decode_bytes
+    #[serde(rename = "memory_budget_exceeded")]
+    MemoryBudgetExceeded, // Could never fit in the shared memory budget
+}
+
+// Error from processing a single file. Plain string errors convert into it
+// without a code, so `?` keeps working on String results.
+#[derive(Debug, Clone)]
+pub struct ProcessError {
+    pub code: Option<ErrorCode>,
+    pub message: String,
+}
+
+impl ProcessError {
+    pub fn new(code: ErrorCode, message: String) -> Self {
+        ProcessError { code: Some(code), message }
+    }
+}
+
+impl From<String> for ProcessError {
+    fn from(message: String) -> Self {
+        ProcessError { code: None, message }
+    }
+}
+
+impl From<&str> for ProcessError {
+    fn from(message: &str) -> Self {
+        ProcessError { code: None, message: message.to_string() }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -127,7 +272,24 @@ pub struct FileResult {
     pub output_width: Option<u32>,
     pub output_height: Option<u32>,
     pub error: Option<String>,
+    pub error_code: Option<ErrorCode>,
     pub backup_info: Option<BackupInfo>,
+    pub settings: Option<ProcessingSettings>, // Effective settings after rules and overrides
+    pub applied_rules: Option<Vec<usize>>, // Indices of the rules that matched this file
+    pub focal_point: Option<FocalPoint>, // Crop center used by cover resizing
+    pub resize_decision: Option<ResizeDecision>, // Why the resize step did or did not change the image
+    pub warnings: Option<Vec<String>>, // Lossy side effects of a successful conversion, e.g. discarded transparency
+}
+
+// What convert_image produced for one file
+struct ConvertOutput {
+    output_path: PathBuf,
+    output_size: u64,
+    output_width: u32,
+    output_height: u32,
+    focal_point: Option<FocalPoint>,
+    resize_decision: Option<ResizeDecision>,
+    warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -136,30 +298,26 @@ pub struct BatchResult {
     pub total: usize,
     pub success_count: usize,
     pub failed_count: usize,
+    pub cached_count: usize,
     pub backups: Vec<BackupInfo>,
 }
 
 fn convert_image(
     input_path: &Path,
     output_dir: &Path,
-    format: Option<&OutputFormat>,
     overwrite: bool,
-    operation_mode: &OperationMode,
-    quality: Option<f32>,
-    resize_mode: Option<&ResizeMode>,
-    resize_percentage: Option<f32>,
-    max_width: Option<u32>,
-    max_height: Option<u32>,
-    keep_aspect_ratio: bool,
-) -> Result<(PathBuf, u64, u32, u32), String> {
-    // Load the image
-    let mut img = image::open(input_path)
-        .map_err(|e| format!("Failed to open image: {}", e))?;
-    
+    settings: &ProcessingSettings,
+    budget: &MemoryBudget,
+) -> Result<ConvertOutput, ProcessError> {
+    let operation_mode = &settings.operation_mode;
+    let quality = settings.quality;
+
+
     // Determine output format: use specified format or detect from original file
-    let output_format = match format {
+    let output_format = match &settings.format {
         Some(fmt) => fmt.clone(),
-        None => OutputFormat::from_path(input_path)
+        // Input-only formats (EXR, HDR, SVG, DDS, HEIF) get a default output
+        None => formats::default_output(input_path)
             .ok_or_else(|| format!("Cannot detect format from: {:?}", input_path))?,
     };
     
@@ -168,39 +326,16 @@ fn convert_image(
         operation_mode,
         OperationMode::Resize | OperationMode::OptimizeResize | OperationMode::All
     );
-    
-    if should_resize {
-        match resize_mode {
-            Some(ResizeMode::Percentage) => {
-                // Resize by percentage
-                if let Some(percentage) = resize_percentage {
-                    let percentage_decimal = (percentage.clamp(1.0, 100.0)) / 100.0;
-                    let (width, height) = img.dimensions();
-                    let new_width = ((width as f32) * percentage_decimal) as u32;
-                    let new_height = ((height as f32) * percentage_decimal) as u32;
-                    
-                    if new_width > 0 && new_height > 0 {
-                        img = img.resize_exact(new_width, new_height, image::imageops::FilterType::Lanczos3);
-                    }
-                }
-            }
-            Some(ResizeMode::Dimensions) | None => {
-                // Resize by dimensions (original behavior)
-                if let (Some(max_w), Some(max_h)) = (max_width, max_height) {
-                    let (width, height) = img.dimensions();
-                    if width > max_w || height > max_h {
-                        if keep_aspect_ratio {
-                            // Resize with aspect ratio (fit within bounds)
-                            img = img.resize(max_w, max_h, image::imageops::FilterType::Lanczos3);
-                        } else {
-                            // Resize exact (may distort image)
-                            img = img.resize_exact(max_w, max_h, image::imageops::FilterType::Lanczos3);
-                        }
-                    }
-                }
-            }
-        }
-    }
+
+    // Refuse oversized images from the header alone, before any pixels are
+    // decoded, then hold their share of the memory budget until we're done
+    let is_svg = svg::is_svg(input_path);
+    let svg_options = settings.svg.clone().unwrap_or_default();
+    let header = decode::read_header(input_path, &svg_options)?;
+    let limits = DecodeLimits::from_settings(settings);
+    decode::check_limits(&header, &limits)?;
+    let _reservation = budget.reserve(MemoryBudget::estimate(&header))?;
+    let source = (header.width, header.height);
 
     // Get the filename without extension
     let stem = input_path
@@ -224,60 +359,211 @@ fn convert_image(
     };
     let output_path = output_dir.join(&output_filename);
 
-
-
     // Ensure output directory exists
     fs::create_dir_all(output_dir)
         .map_err(|e| format!("Failed to create output directory: {}", e))?;
 
+    // JPEG to JPEG and PNG to PNG in Optimize mode can skip decoding entirely
+    // and recompress the source bytes, so the pixels don't change at all
+    let jpeg_options = settings.jpeg.clone().unwrap_or_default();
+    let png_options = settings.png.clone().unwrap_or_default();
+    let lossless_rewrite = matches!(operation_mode, OperationMode::Optimize)
+        && match (OutputFormat::from_path(input_path), &output_format) {
+            (Some(OutputFormat::Jpeg), OutputFormat::Jpeg) => jpeg_options.lossless.unwrap_or(false),
+            (Some(OutputFormat::Png), OutputFormat::Png) => png_options.lossless.unwrap_or(false),
+            _ => false,
+        };
+    if lossless_rewrite {
+        let source_data = fs::read(input_path)
+            .map_err(|e| format!("Failed to read image: {}", e))?;
+        let optimized = match output_format {
+            OutputFormat::Jpeg => jpeg::optimize_lossless(&source_data, &jpeg_options)?,
+            _ => png_encode::optimize_png(&source_data, &png_options)?,
+        };
+        fs::write(&output_path, &optimized)
+            .map_err(|e| format!("Failed to write image: {}", e))?;
+
+        return Ok(ConvertOutput {
+            output_path,
+            output_size: optimized.len() as u64,
+            output_width: header.width,
+            output_height: header.height,
+            focal_point: None,
+            resize_decision: None,
+            warnings: Vec::new(),
+        });
+    }
+
+    // Plan the resize from the header so the fast backend can shrink on load
+    let resize_plan = if should_resize {
+        Some((resize::plan_resize(source, settings)?, source))
+    } else {
+        None
+    };
+    let shrink_hint = match (&resize_plan, settings.resize_backend) {
+        (Some((plan, source)), Some(ResizeBackend::Fast)) => plan.decode_hint(*source),
+        _ => None,
+    };
+
+    // Load the image
+    let mut img = if is_svg {
+        svg::rasterize(input_path, &svg_options)?
+    } else {
+        decode::load_image(input_path, shrink_hint, &limits)?
+    };
+
     // Determine if we should apply optimization/quality settings
     let should_optimize = matches!(
         operation_mode,
         OperationMode::Optimize | OperationMode::OptimizeResize | OperationMode::All
     );
 
+    // Reduce sample depth only as far as the encoder requires, and report it
+    let mut warnings = Vec::new();
+    let quantizes_png = matches!(output_format, OutputFormat::Png)
+        && should_optimize
+        && !png_options.lossless.unwrap_or(false);
+    let (max_depth, target) = if quantizes_png {
+        (SampleDepth::Eight, "palette-quantized PNG".to_string())
+    } else {
+        (output_format.max_sample_depth(), output_format.extension().to_uppercase())
+    };
+    let preserve_bit_depth = settings.preserve_bit_depth.unwrap_or(true);
+    let max_depth = if preserve_bit_depth { max_depth } else { SampleDepth::Eight };
+
+    // Scene-linear HDR values above 1.0 would clip, so bring them into display
+    // range first. This happens before resizing so that `contain` padding, which
+    // is a display color, is not tone mapped along with the pixels.
+    let tone_maps = tonemap::is_hdr_source(input_path)
+        && SampleDepth::of(img.color()) > max_depth;
+    if tone_maps {
+        let tone_map = settings.tone_map.unwrap_or_default();
+        img = tonemap::tone_map(img, &tone_map);
+        warnings.push(format!(
+            "Tone mapped HDR samples to {} with the {} operator for {} output",
+            max_depth.describe(),
+            tone_map.operator.unwrap_or_default().name(),
+            target
+        ));
+    }
+
+    let mut focal_point = None;
+    let mut resize_decision = None;
+    if let Some((plan, source)) = &resize_plan {
+        let outcome = resize::apply_plan(img, plan, *source, settings);
+        img = outcome.image;
+        focal_point = outcome.focal_point;
+        resize_decision = Some(outcome.decision);
+    }
+
+    let (reduced, original_depth) = depth::limit_depth(img, max_depth);
+    img = reduced;
+    if let Some(original_depth) = original_depth.filter(|_| preserve_bit_depth && !tone_maps) {
+        warnings.push(format!(
+            "Reduced {} samples to {} because {} output can't store more",
+            original_depth.describe(),
+            max_depth.describe(),
+            target
+        ));
+    }
+
+    // Drop channels the pixels don't use before encoding to lossless-capable formats
+    let reduces_color = matches!(
+        output_format,
+        OutputFormat::Png | OutputFormat::Tiff | OutputFormat::Webp | OutputFormat::Bmp
+            | OutputFormat::Tga | OutputFormat::Pnm
+    );
+    let analysis = (reduces_color && settings.reduce_color_type.unwrap_or(true))
+        .then(|| reduce::analyze(&img));
+    if let Some(analysis) = &analysis {
+        img = reduce::reduce_channels(img, analysis);
+    }
+
+    // Flatten transparency onto the matte for formats that can't store it
+    if !output_format.supports_alpha() {
+        let format_name = output_format.extension().to_uppercase();
+        let (flattened, warning) = matte::flatten_if_transparent(img, settings.alpha_matte.as_deref(), &format_name)?;
+        img = flattened;
+        warnings.extend(warning);
+    }
+
+    // GIF has a single transparent palette entry; every other pixel is opaque
+    if matches!(output_format, OutputFormat::Gif) && matte::has_partial_transparency(&img) {
+        warnings.push("Partially transparent pixels were made opaque because GIF has no partial transparency".to_string());
+    }
+
     // Save the image in the target format
     match &output_format {
         OutputFormat::Webp => {
+            let webp_options = settings.webp.clone().unwrap_or_default();
             if should_optimize {
-                // Use lossy WebP encoding with quality control
+                // Use lossy WebP encoding with quality control, unless lossless is requested
                 let quality_val = quality.unwrap_or(75.0).clamp(0.0, 100.0);
-                save_webp_lossy(&img, &output_path, quality_val)?;
+                webp_encode::save_webp(&img, &output_path, quality_val, &webp_options)?;
             } else {
                 // Use lossless for Convert mode
-                save_webp_lossless(&img, &output_path)?;
+                webp_encode::save_webp_lossless(&img, &output_path, &webp_options)?;
             }
         }
         OutputFormat::Png => {
-            if should_optimize {
+            if quantizes_png {
                 // Use PNG with pngquant compression
                 let quality_val = quality.unwrap_or(90.0).clamp(0.0, 100.0) as u8;
-                save_png_compressed(&img, &output_path, quality_val)?;
+                let fallback = png_encode::save_png_quantized(
+                    &img, &output_path, quality_val, &png_options, analysis.as_ref(),
+                )?;
+                warnings.extend(fallback);
             } else {
-                // Use standard PNG encoder
-                img.save(&output_path)
-                    .map_err(|e| format!("Failed to save PNG: {}", e))?;
+                // Lossless PNG, recompressed with oxipng
+                png_encode::save_png_lossless(&img, &output_path, &png_options, analysis.as_ref())?;
             }
         }
         OutputFormat::Jpeg => {
             if should_optimize {
                 // Use JPEG with quality control
                 let quality_val = quality.unwrap_or(85.0).clamp(0.0, 100.0) as u8;
-                save_jpeg_with_quality(&img, &output_path, quality_val)?;
+                jpeg::save_jpeg(&img, &output_path, quality_val, &jpeg_options)?;
             } else {
                 // Use standard JPEG encoder with high quality
-                save_jpeg_with_quality(&img, &output_path, 95)?;
+                jpeg::save_jpeg(&img, &output_path, 95, &jpeg_options)?;
             }
         }
         OutputFormat::Qoi => {
             // QOI format
             save_qoi(&img, &output_path)?;
         }
+        OutputFormat::Bmp => {
+            bmp_encode::save_bmp(&img, &output_path, analysis.as_ref())?;
+        }
+        OutputFormat::Pnm => {
+            save_pnm(&img, &output_path)?;
+        }
+        OutputFormat::Avif => {
+            // AVIF has no lossless mode, so Convert mode uses a high quality
+            let quality_val = if should_optimize { quality.unwrap_or(70.0) } else { 90.0 };
+            avif_encode::save_avif(&img, &output_path, quality_val.clamp(0.0, 100.0) as u8)?;
+        }
         _ => {
-            // Use image crate for TIFF, BMP
+            // Use image crate for TIFF, TGA, ICO and GIF
             let image_format = output_format.to_image_format()
                 .ok_or("Unsupported format")?;
-            img.save_with_format(&output_path, image_format)
+            let (width, height) = img.dimensions();
+            if matches!(output_format, OutputFormat::Ico) && (width > 256 || height > 256) {
+                return Err(format!(
+                    "ICO images can be at most 256×256, this one is {}×{}; resize it or generate an icon set",
+                    width, height
+                ).into());
+            }
+            // TIFF has no gray + alpha layout, GIF only takes RGB(A)
+            let expanded = match (&output_format, img.color()) {
+                (OutputFormat::Tiff, ColorType::La8) => Some(DynamicImage::ImageRgba8(img.to_rgba8())),
+                (OutputFormat::Tiff, ColorType::La16) => Some(DynamicImage::ImageRgba16(img.to_rgba16())),
+                (OutputFormat::Gif, color) if color.has_alpha() => Some(DynamicImage::ImageRgba8(img.to_rgba8())),
+                (OutputFormat::Gif, _) => Some(DynamicImage::ImageRgb8(img.to_rgb8())),
+                _ => None,
+            };
+            expanded.as_ref().unwrap_or(&img)
+                .save_with_format(&output_path, image_format)
                 .map_err(|e| format!("Failed to save image: {}", e))?;
         }
     }
@@ -289,143 +575,42 @@ fn convert_image(
     
     let (output_width, output_height) = img.dimensions();
 
-    Ok((output_path, output_size, output_width, output_height))
-}
-
-fn save_webp_lossy(img: &DynamicImage, output_path: &Path, quality: f32) -> Result<(), String> {
-    let rgba = img.to_rgba8();
-    let (width, height) = rgba.dimensions();
-    
-    let encoder = webp::Encoder::from_rgba(&rgba, width, height);
-    // Use lossy encoding with quality parameter (0-100)
-    let webp_data = encoder.encode(quality);
-    
-    fs::write(output_path, &*webp_data)
-        .map_err(|e| format!("Failed to write WebP file: {}", e))?;
-    
-    Ok(())
-}
-
-fn save_webp_lossless(img: &DynamicImage, output_path: &Path) -> Result<(), String> {
-    let rgba = img.to_rgba8();
-    let (width, height) = rgba.dimensions();
-    
-    let encoder = webp::Encoder::from_rgba(&rgba, width, height);
-    // Use lossless encoding
-    let webp_data = encoder.encode_lossless();
-    
-    fs::write(output_path, &*webp_data)
-        .map_err(|e| format!("Failed to write WebP file: {}", e))?;
-    
-    Ok(())
-}
-
-fn save_png_compressed(img: &DynamicImage, output_path: &Path, quality: u8) -> Result<(), String> {
-    // Use pngquant algorithm (imagequant) for lossy compression with quality control
-    let rgba = img.to_rgba8();
-    let (width, height) = rgba.dimensions();
-    
-    // Convert ImageBuffer to RGBA format for imagequant
-    let rgba_data: Vec<imagequant::RGBA> = rgba.pixels()
-        .map(|p| imagequant::RGBA::new(p[0], p[1], p[2], p[3]))
-        .collect();
-    
-    // Create attributes for pngquant
-    let mut liq = imagequant::new();
-    
-    // Set quality range (min 0, max quality)
-    // Higher quality = more colors preserved
-    liq.set_quality(0, quality)
-        .map_err(|e| format!("Failed to set quality: {:?}", e))?;
-    
-    // Speed vs quality tradeoff (1-10, 1=best quality/slowest, 10=worst/fastest)
-    liq.set_speed(5)
-        .map_err(|e| format!("Failed to set speed: {:?}", e))?;
-    
-    // Create image for quantization
-    let mut img_data = liq.new_image(
-        rgba_data.into_boxed_slice(),
-        width as usize,
-        height as usize,
-        0.0
-    ).map_err(|e| format!("Failed to create image: {:?}", e))?;
-    
-    // Quantize (reduce colors)
-    let mut result = liq.quantize(&mut img_data)
-        .map_err(|e| format!("Failed to quantize: {:?}", e))?;
-    
-    // Set dithering level (0.0 - 1.0)
-    result.set_dithering_level(1.0)
-        .map_err(|e| format!("Failed to set dithering: {:?}", e))?;
-    
-    // Get quantized image data
-    let (palette, pixels) = result.remapped(&mut img_data)
-        .map_err(|e| format!("Failed to remap: {:?}", e))?;
-    
-    // Write PNG with oxipng optimization
-    write_optimized_png(output_path, &pixels, &palette, width, height)?;
-    
-    Ok(())
+    Ok(ConvertOutput {
+        output_path,
+        output_size,
+        output_width,
+        output_height,
+        focal_point,
+        resize_decision,
+        warnings,
+    })
 }
 
-fn write_optimized_png(
-    output_path: &Path,
-    pixels: &[u8],
-    palette: &[imagequant::RGBA],
-    width: u32,
-    height: u32,
-) -> Result<(), String> {
-    use std::io::BufWriter;
-    
-    // First write to a temporary buffer
-    let mut png_data = Vec::new();
-    {
-        let writer = BufWriter::new(&mut png_data);
-        let mut encoder = png::Encoder::new(writer, width, height);
-        encoder.set_color(png::ColorType::Indexed);
-        encoder.set_depth(png::BitDepth::Eight);
-        
-        // Convert palette to PNG format
-        let palette_rgb: Vec<u8> = palette.iter()
-            .flat_map(|c| [c.r, c.g, c.b])
-            .collect();
-        encoder.set_palette(palette_rgb);
-        
-        // Set transparency if needed
-        let has_alpha = palette.iter().any(|c| c.a < 255);
-        if has_alpha {
-            let trns: Vec<u8> = palette.iter().map(|c| c.a).collect();
-            encoder.set_trns(trns);
-        }
-        
-        let mut writer = encoder.write_header()
-            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
-        writer.write_image_data(pixels)
-            .map_err(|e| format!("Failed to write PNG data: {}", e))?;
-    }
-    
-    // Optimize with oxipng
-    let options = oxipng::Options {
-        strip: oxipng::StripChunks::Safe,
-        optimize_alpha: true,
-        ..oxipng::Options::max_compression()
+// Plain PGM/PPM where the layout allows, since more tools read those than PAM
+fn save_pnm(img: &DynamicImage, output_path: &Path) -> Result<(), String> {
+    let subtype = match img.color() {
+        ColorType::L8 => PnmSubtype::Graymap(SampleEncoding::Binary),
+        ColorType::Rgb8 => PnmSubtype::Pixmap(SampleEncoding::Binary),
+        _ => PnmSubtype::ArbitraryMap,
     };
-    
-    let optimized = oxipng::optimize_from_memory(&png_data, &options)
-        .map_err(|e| format!("Failed to optimize PNG: {}", e))?;
-    
-    fs::write(output_path, optimized)
-        .map_err(|e| format!("Failed to write optimized PNG: {}", e))?;
-    
-    Ok(())
+    let file = fs::File::create(output_path)
+        .map_err(|e| format!("Failed to create PNM file: {}", e))?;
+    let encoder = PnmEncoder::new(BufWriter::new(file)).with_subtype(subtype);
+    img.write_with_encoder(encoder)
+        .map_err(|e| format!("Failed to save image: {}", e))
 }
 
 fn save_qoi(img: &DynamicImage, output_path: &Path) -> Result<(), String> {
-    let rgba = img.to_rgba8();
-    let (width, height) = rgba.dimensions();
+    let (width, height) = img.dimensions();
+    // QOI stores 3 or 4 channels; skip the alpha channel when there is none
+    let pixels = if img.color().has_alpha() {
+        img.to_rgba8().into_raw()
+    } else {
+        img.to_rgb8().into_raw()
+    };
     
     let qoi_data = qoi::encode_to_vec(
-        rgba.as_raw(),
+        &pixels,
         width,
         height,
     ).map_err(|e| format!("Failed to encode QOI: {}", e))?;
@@ -436,147 +621,243 @@ fn save_qoi(img: &DynamicImage, output_path: &Path) -> Result<(), String> {
     Ok(())
 }
 
-fn save_jpeg_with_quality(img: &DynamicImage, output_path: &Path, quality: u8) -> Result<(), String> {
-    use std::io::BufWriter;
-    use image::codecs::jpeg::JpegEncoder;
-    
-    let rgb = img.to_rgb8();
-    let (width, height) = rgb.dimensions();
-    
-    let file = fs::File::create(output_path)
-        .map_err(|e| format!("Failed to create JPEG file: {}", e))?;
-    let mut writer = BufWriter::new(file);
-    
-    let mut encoder = JpegEncoder::new_with_quality(&mut writer, quality);
-    encoder.encode(rgb.as_raw(), width, height, image::ExtendedColorType::Rgb8)
-        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
-    
-    Ok(())
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProgressUpdate {
     pub current: usize,
     pub total: usize,
     pub success_count: usize,
     pub failed_count: usize,
+    pub cached_count: usize,
     pub current_file: Option<String>,
 }
 
+impl FileResult {
+    fn failed(path: &str, error: impl Into<ProcessError>) -> Self {
+        let error = error.into();
+        FileResult {
+            path: path.to_string(),
+            status: FileStatus::Failed,
+            output_path: None,
+            output_size: None,
+            output_width: None,
+            output_height: None,
+            error: Some(error.message),
+            error_code: error.code,
+            backup_info: None,
+            settings: None,
+            applied_rules: None,
+            focal_point: None,
+            resize_decision: None,
+            warnings: None,
+        }
+    }
+}
+
+// Process a single file: resolve its effective settings, then convert it
+fn process_file(
+    path_str: &str,
+    output_dir: &str,
+    overwrite: bool,
+    settings: &ProcessingSettings,
+    path_override: Option<&SettingsPatch>,
+    cache: Option<&ProcessingCache>,
+    budget: &MemoryBudget,
+) -> FileResult {
+    let (settings, applied_rules) = match rules::resolve_settings(settings, Path::new(path_str), path_override) {
+        Ok(resolved) => resolved,
+        Err(e) => return FileResult::failed(path_str, e),
+    };
+
+    let mut result = process_resolved_file(path_str, output_dir, overwrite, &settings, cache, budget);
+    result.settings = Some(settings);
+    result.applied_rules = Some(applied_rules);
+    result
+}
+
+// Convert a file with already-resolved settings, consulting the cache first
+// when one is given
+fn process_resolved_file(
+    path_str: &str,
+    output_dir: &str,
+    overwrite: bool,
+    settings: &ProcessingSettings,
+    cache: Option<&ProcessingCache>,
+    budget: &MemoryBudget,
+) -> FileResult {
+    let input_path = Path::new(path_str);
+
+    // If overwrite is true and output_dir is empty, use the input file's directory
+    let output_dir = if overwrite && output_dir.is_empty() {
+        input_path.parent().unwrap_or(Path::new("."))
+    } else {
+        Path::new(output_dir)
+    };
+
+    // Hashing failures are not fatal, the file is just processed uncached
+    let cache_key = cache.and_then(|_| {
+        cache::hash_file(input_path)
+            .ok()
+            .map(|hash| cache::cache_key(input_path, &hash, output_dir, overwrite, settings))
+    });
+
+    if let (Some(cache), Some(key)) = (cache, &cache_key) {
+        if let Some(entry) = cache.lookup(key) {
+            return FileResult {
+                path: path_str.to_string(),
+                status: FileStatus::Cached,
+                output_path: Some(entry.output_path),
+                output_size: Some(entry.output_size),
+                output_width: Some(entry.output_width),
+                output_height: Some(entry.output_height),
+                error: None,
+                error_code: None,
+                backup_info: None,
+                settings: None,
+                applied_rules: None,
+                focal_point: entry.focal_point,
+                resize_decision: entry.resize_decision,
+                warnings: (!entry.warnings.is_empty()).then_some(entry.warnings),
+            };
+        }
+    }
+
+    match convert_image(input_path, output_dir, overwrite, settings, budget) {
+        Ok(output) => {
+            if let (Some(cache), Some(key)) = (cache, cache_key) {
+                if let Some((size, modified)) = cache::file_stamp(&output.output_path) {
+                    let entry = CacheEntry {
+                        output_path: output.output_path.to_string_lossy().to_string(),
+                        output_size: size,
+                        output_modified: modified,
+                        output_width: output.output_width,
+                        output_height: output.output_height,
+                        focal_point: output.focal_point,
+                        resize_decision: output.resize_decision,
+                        warnings: output.warnings.clone(),
+                    };
+
+                    // When the output replaced the source in place, the next run
+                    // sees the output's content as the source, so key it as well
+                    if output.output_path == input_path {
+                        if let Ok(output_hash) = cache::hash_file(&output.output_path) {
+                            let output_key = cache::cache_key(input_path, &output_hash, output_dir, overwrite, settings);
+                            cache.insert(output_key, entry.clone());
+                        }
+                    }
+                    cache.insert(key, entry);
+                }
+            }
+
+            FileResult {
+                path: path_str.to_string(),
+                status: FileStatus::Success,
+                output_path: Some(output.output_path.to_string_lossy().to_string()),
+                output_size: Some(output.output_size),
+                output_width: Some(output.output_width),
+                output_height: Some(output.output_height),
+                error: None,
+                error_code: None,
+                backup_info: None,
+                settings: None,
+                applied_rules: None,
+                focal_point: output.focal_point,
+                resize_decision: output.resize_decision,
+                warnings: (!output.warnings.is_empty()).then_some(output.warnings),
+            }
+        }
+        Err(e) => FileResult::failed(path_str, e),
+    }
+}
+
 #[tauri::command]
 async fn optimize_batch(request: OptimizeBatchRequest, app: tauri::AppHandle, cancel_flag: State<'_, CancellationFlag>) -> Result<BatchResult, String> {
     let cancel_flag = cancel_flag.0.clone();
     
     tokio::task::spawn_blocking(move || {
+        let cache = app.state::<ProcessingCache>();
+        let budget = app.state::<MemoryBudget>();
+        let use_cache = request.use_cache.unwrap_or(true);
+
         let mut results = Vec::new();
         let mut success_count = 0;
         let mut failed_count = 0;
+        let mut cached_count = 0;
         let total = request.paths.len();
 
-
         for (index, path_str) in request.paths.iter().enumerate() {
             // Check if cancellation was requested
             if cancel_flag.load(Ordering::Relaxed) {
                 // Mark remaining files as failed with cancellation message
                 for remaining_path in request.paths.iter().skip(index) {
-                    results.push(FileResult {
-                        path: remaining_path.clone(),
-                        status: FileStatus::Failed,
-                        output_path: None,
-                        output_size: None,
-                        output_width: None,
-                        output_height: None,
-                        error: Some("Processing cancelled by user".to_string()),
-                        backup_info: None,
-                    });
+                    results.push(FileResult::failed(remaining_path, "Processing cancelled by user"));
                     failed_count += 1;
                 }
                 break;
             }
 
-            let input_path = Path::new(path_str);
-            
             // Emit progress event before processing
             let _ = app.emit("progress-update", ProgressUpdate {
                 current: index,
                 total,
                 success_count,
                 failed_count,
+                cached_count,
                 current_file: Some(path_str.clone()),
             });
-        
-        // No backup needed
-        
-        // If overwrite is true and output_dir is empty, use the input file's directory
-        let output_dir = if request.overwrite && request.output_dir.is_empty() {
-            input_path.parent().unwrap_or(Path::new("."))
-        } else {
-            Path::new(&request.output_dir)
-        };
-        
-        match convert_image(
-            input_path,
-            output_dir,
-            request.format.as_ref(),
-            request.overwrite,
-            &request.operation_mode,
-            request.quality,
-            request.resize_mode.as_ref(),
-            request.resize_percentage,
-            request.max_width,
-            request.max_height,
-            request.keep_aspect_ratio.unwrap_or(true),
-        ) {
-            Ok((output_path, output_size, output_width, output_height)) => {
-                results.push(FileResult {
-                    path: path_str.clone(),
-                    status: FileStatus::Success,
-                    output_path: Some(output_path.to_string_lossy().to_string()),
-                    output_size: Some(output_size),
-                    output_width: Some(output_width),
-                    output_height: Some(output_height),
-                    error: None,
-                    backup_info: None,
-                });
-                success_count += 1;
-            }
-            Err(e) => {
-                results.push(FileResult {
-                    path: path_str.clone(),
-                    status: FileStatus::Failed,
-                    output_path: None,
-                    output_size: None,
-                    output_width: None,
-                    output_height: None,
-                    error: Some(e),
-                    backup_info: None,
-                });
-                failed_count += 1;
+
+            let result = process_file(
+                path_str,
+                &request.output_dir,
+                request.overwrite,
+                &request.settings,
+                request.overrides.as_ref().and_then(|overrides| overrides.get(path_str)),
+                use_cache.then_some(&*cache),
+                &budget,
+            );
+
+            match result.status {
+                FileStatus::Cached => cached_count += 1,
+                FileStatus::Failed => failed_count += 1,
+                _ => success_count += 1,
             }
+            let _ = app.emit("file-result", &result);
+            results.push(result);
+
+            // Emit progress event after processing
+            let _ = app.emit("progress-update", ProgressUpdate {
+                current: index + 1,
+                total,
+                success_count,
+                failed_count,
+                cached_count,
+                current_file: None,
+            });
         }
-        
-        // Emit progress event after processing
-        let _ = app.emit("progress-update", ProgressUpdate {
-            current: index + 1,
-            total,
+
+        if use_cache {
+            // A cache that fails to persist only costs a recompression next run
+            let _ = cache.save();
+        }
+
+        BatchResult {
+            total: request.paths.len(),
+            results,
             success_count,
             failed_count,
-            current_file: None,
-        });
-    }
-
-    BatchResult {
-        total: request.paths.len(),
-        results,
-        success_count,
-        failed_count,
-        backups: Vec::new(),
-    }
+            cached_count,
+            backups: Vec::new(),
+        }
     })
     .await
     .map_err(|e| format!("Failed to execute batch processing: {}", e))
 }
 
+#[tauri::command]
+fn clear_processing_cache(cache: State<ProcessingCache>) -> Result<String, String> {
+    cache.clear()?;
+    Ok("Processing cache cleared".to_string())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageDimensions {
     pub width: u32,
@@ -585,65 +866,19 @@ pub struct ImageDimensions {
 
 #[tauri::command]
 fn get_image_dimensions(path: String) -> Result<ImageDimensions, String> {
-    let img = image::open(&path)
-        .map_err(|e| format!("Failed to open image: {}", e))?;
-    let (width, height) = img.dimensions();
+    // Header only, so huge images can't exhaust memory just by being inspected
+    let (width, height) = decode::image_dimensions(Path::new(&path))?;
     Ok(ImageDimensions { width, height })
 }
 
 #[tauri::command]
-fn get_supported_formats() -> Vec<String> {
-    vec![
-        "png".to_string(),
-        "webp".to_string(),
-        "tiff".to_string(),
-        "qoi".to_string(),
-        "bmp".to_string(),
-    ]
+fn get_supported_formats() -> Vec<OutputFormat> {
+    formats::output_formats()
 }
 
 #[tauri::command]
-fn scan_folder_for_images(folder_path: String) -> Result<Vec<String>, String> {
-    use std::fs;
-    
-    let supported_extensions = ["png", "jpg", "jpeg", "webp", "tiff", "tif", "qoi", "bmp"];
-    let mut image_paths = Vec::new();
-    
-    fn scan_directory(dir: &Path, extensions: &[&str], paths: &mut Vec<String>) -> Result<(), String> {
-        if !dir.is_dir() {
-            return Err("Path is not a directory".to_string());
-        }
-        
-        let entries = fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read directory: {}", e))?;
-        
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let path = entry.path();
-            
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if let Some(ext_str) = ext.to_str() {
-                        if extensions.contains(&ext_str.to_lowercase().as_str()) {
-                            if let Some(path_str) = path.to_str() {
-                                paths.push(path_str.to_string());
-                            }
-                        }
-                    }
-                }
-            } else if path.is_dir() {
-                // Recursive scan
-                scan_directory(&path, extensions, paths)?;
-            }
-        }
-        
-        Ok(())
-    }
-    
-    let folder = Path::new(&folder_path);
-    scan_directory(folder, &supported_extensions, &mut image_paths)?;
-    
-    Ok(image_paths)
+fn get_supported_input_extensions() -> Vec<&'static str> {
+    formats::input_extensions()
 }
 
 #[tauri::command]
@@ -742,13 +977,36 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .manage(CancellationFlag(Arc::new(AtomicBool::new(false))))
+        .manage(ScanCancellationFlag(Arc::new(AtomicBool::new(false))))
+        .manage(WatchRegistry::default())
+        .manage(MemoryBudget::default())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(ProcessingCache::load(data_dir.join("processing_cache.json")));
+            app.manage(PresetStore::load(data_dir.join("presets.json")));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             optimize_batch,
             cancel_batch,
             reset_cancel_flag,
+            clear_processing_cache,
+            memory::set_memory_budget,
             get_supported_formats,
+            get_supported_input_extensions,
             get_image_dimensions,
-            scan_folder_for_images,
+            icons::generate_icon_set,
+            scan::scan_folder_for_images,
+            scan::cancel_scan,
+            watch::start_watch,
+            watch::stop_watch,
+            watch::list_watches,
+            presets::list_presets,
+            presets::create_preset,
+            presets::update_preset,
+            presets::delete_preset,
+            presets::import_presets,
+            presets::export_presets,
             create_backup,
             restore_from_backup,
             delete_backup
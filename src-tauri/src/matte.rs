@@ -0,0 +1,219 @@
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+use crate::resize::parse_hex_color;
+
+const WHITE: [u8; 3] = [255, 255, 255];
+const BLACK: [u8; 3] = [0, 0, 0];
+// Light and dark squares of the usual transparency grid
+const CHECKER_COLORS: [[u8; 3]; 2] = [[255, 255, 255], [204, 204, 204]];
+const CHECKER_SIZE: u32 = 8;
+// Average luma of the visible pixels above which "auto" switches to black
+const AUTO_LIGHT_THRESHOLD: f64 = 230.0;
+
+// Background that transparent pixels are composited onto when the output
+// format can't store alpha
+#[derive(Debug, Clone, Copy)]
+pub enum Matte {
+    Color([u8; 3]),
+    Checker, // Transparency grid, so the flattened image still shows what was transparent
+    Auto, // White, or black when the visible content is almost all white
+}
+
+impl Default for Matte {
+    fn default() -> Self {
+        Matte::Color(WHITE)
+    }
+}
+
+impl Matte {
+    // "auto", "checker" or a "#RGB" / "#RRGGBB" color; alpha in "#RRGGBBAA" is ignored
+    pub fn parse(value: &str) -> Result<Matte, String> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Ok(Matte::Auto),
+            "checker" => Ok(Matte::Checker),
+            _ => {
+                let color = parse_hex_color(value)?;
+                Ok(Matte::Color([color[0], color[1], color[2]]))
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Matte::Color(color) => format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]),
+            Matte::Checker => "a checkerboard".to_string(),
+            Matte::Auto => "an automatic background".to_string(),
+        }
+    }
+}
+
+// Whether any pixel is neither fully opaque nor fully transparent
+pub fn has_partial_transparency(img: &DynamicImage) -> bool {
+    match img {
+        DynamicImage::ImageLumaA8(buf) => buf.pixels().any(|p| p[1] != 0 && p[1] != u8::MAX),
+        DynamicImage::ImageRgba8(buf) => buf.pixels().any(|p| p[3] != 0 && p[3] != u8::MAX),
+        DynamicImage::ImageLumaA16(buf) => buf.pixels().any(|p| p[1] != 0 && p[1] != u16::MAX),
+        DynamicImage::ImageRgba16(buf) => buf.pixels().any(|p| p[3] != 0 && p[3] != u16::MAX),
+        DynamicImage::ImageRgba32F(buf) => buf.pixels().any(|p| p[3] > 0.0 && p[3] < 1.0),
+        _ => false,
+    }
+}
+
+// Whether any pixel is less than fully opaque
+pub fn has_transparency(img: &DynamicImage) -> bool {
+    match img {
+        DynamicImage::ImageLumaA8(buf) => buf.pixels().any(|p| p[1] != u8::MAX),
+        DynamicImage::ImageRgba8(buf) => buf.pixels().any(|p| p[3] != u8::MAX),
+        DynamicImage::ImageLumaA16(buf) => buf.pixels().any(|p| p[1] != u16::MAX),
+        DynamicImage::ImageRgba16(buf) => buf.pixels().any(|p| p[3] != u16::MAX),
+        DynamicImage::ImageRgba32F(buf) => buf.pixels().any(|p| p[3] < 1.0),
+        _ => false,
+    }
+}
+
+// Resolve "auto" by looking at what is actually visible: light logos and
+// text on a transparent background would vanish on white
+fn resolve_auto(img: &DynamicImage) -> [u8; 3] {
+    let mut luma_sum = 0.0;
+    let mut weight_sum = 0.0;
+    for (_, _, pixel) in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        let weight = a as f64 / 255.0;
+        luma_sum += (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) * weight;
+        weight_sum += weight;
+    }
+
+    if weight_sum > 0.0 && luma_sum / weight_sum > AUTO_LIGHT_THRESHOLD {
+        BLACK
+    } else {
+        WHITE
+    }
+}
+
+// Composite the image over the matte and drop the alpha channel. Returns the
+// flattened image and a description of the background used.
+pub fn flatten(img: &DynamicImage, matte: Matte) -> (DynamicImage, String) {
+    let matte = match matte {
+        Matte::Auto => Matte::Color(resolve_auto(img)),
+        other => other,
+    };
+
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let flattened = RgbImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let background = match matte {
+            Matte::Color(color) => color,
+            _ => CHECKER_COLORS[((x / CHECKER_SIZE + y / CHECKER_SIZE) % 2) as usize],
+        };
+        let alpha = a as u32;
+        let blend = |fg: u8, bg: u8| ((fg as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8;
+        Rgb([blend(r, background[0]), blend(g, background[1]), blend(b, background[2])])
+    });
+
+    (DynamicImage::ImageRgb8(flattened), matte.describe())
+}
+
+// Flatten onto the `alpha_matte` setting when the image has any transparency,
+// for output formats that can't store it. Returns the image and the warning
+// to report, which is None when the image was already opaque.
+pub fn flatten_if_transparent(
+    img: DynamicImage,
+    alpha_matte: Option<&str>,
+    format_name: &str,
+) -> Result<(DynamicImage, Option<String>), String> {
+    if !has_transparency(&img) {
+        return Ok((img, None));
+    }
+    let matte = match alpha_matte {
+        Some(value) => Matte::parse(value)?,
+        None => Matte::default(),
+    };
+    let (flattened, background) = flatten(&img, matte);
+    let warning = format!(
+        "Transparency was flattened onto {} because {} has no alpha channel",
+        background, format_name
+    );
+    Ok((flattened, Some(warning)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn solid(color: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 16, Rgba(color)))
+    }
+
+    fn flattened_pixel(color: [u8; 4], matte: Matte, x: u32, y: u32) -> [u8; 3] {
+        flatten(&solid(color), matte).0.to_rgb8().get_pixel(x, y).0
+    }
+
+    #[test]
+    fn flatten_blends_by_alpha() {
+        let white = Matte::default();
+        assert_eq!(flattened_pixel([255, 0, 0, 0], white, 0, 0), [255, 255, 255]);
+        assert_eq!(flattened_pixel([255, 0, 0, 128], white, 0, 0), [255, 127, 127]);
+        assert_eq!(flattened_pixel([255, 0, 0, 255], white, 0, 0), [255, 0, 0]);
+        assert_eq!(flattened_pixel([255, 0, 0, 0], Matte::Color([0, 0, 255]), 0, 0), [0, 0, 255]);
+    }
+
+    #[test]
+    fn checker_alternates_every_eight_pixels() {
+        assert_eq!(flattened_pixel([0, 0, 0, 0], Matte::Checker, 0, 0), [255, 255, 255]);
+        assert_eq!(flattened_pixel([0, 0, 0, 0], Matte::Checker, 8, 0), [204, 204, 204]);
+        assert_eq!(flattened_pixel([0, 0, 0, 0], Matte::Checker, 8, 8), [255, 255, 255]);
+    }
+
+    #[test]
+    fn auto_picks_black_for_white_content() {
+        assert_eq!(resolve_auto(&solid([255, 255, 255, 255])), BLACK);
+        assert_eq!(resolve_auto(&solid([40, 40, 40, 255])), WHITE);
+        // Nothing visible: the default stays
+        assert_eq!(resolve_auto(&solid([255, 255, 255, 0])), WHITE);
+
+        let (_, background) = flatten(&solid([255, 255, 255, 200]), Matte::Auto);
+        assert_eq!(background, "#000000");
+    }
+
+    #[test]
+    fn parse_accepts_keywords_and_hex_colors() {
+        assert!(matches!(Matte::parse(" AUTO "), Ok(Matte::Auto)));
+        assert!(matches!(Matte::parse("checker"), Ok(Matte::Checker)));
+        assert!(matches!(Matte::parse("#abc"), Ok(Matte::Color([170, 187, 204]))));
+        assert!(matches!(Matte::parse("102030ff"), Ok(Matte::Color([16, 32, 48]))));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_colors() {
+        for value in ["", "white", "#12", "#12345", "#gggggg"] {
+            assert!(Matte::parse(value).is_err(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn partial_transparency_ignores_fully_clear_and_opaque_pixels() {
+        let mut img = RgbaImage::from_pixel(2, 1, Rgba([0, 0, 0, 0]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        assert!(has_transparency(&img));
+        assert!(!has_partial_transparency(&img));
+        assert!(has_partial_transparency(&solid([0, 0, 0, 128])));
+    }
+
+    #[test]
+    fn transparency_is_flattened_with_a_warning() {
+        let (img, warning) = flatten_if_transparent(solid([0, 0, 0, 0]), Some("checker"), "JPEG").unwrap();
+        assert_eq!(img.color(), image::ColorType::Rgb8);
+        let warning = warning.unwrap();
+        assert!(warning.contains("checkerboard") && warning.contains("JPEG"), "{}", warning);
+
+        let opaque = solid([10, 20, 30, 255]);
+        let (img, warning) = flatten_if_transparent(opaque.clone(), Some("checker"), "JPEG").unwrap();
+        assert!(warning.is_none());
+        assert_eq!(img, opaque);
+
+        assert!(flatten_if_transparent(solid([0, 0, 0, 0]), Some("nope"), "JPEG").is_err());
+    }
+}
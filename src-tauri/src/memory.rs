@@ -0,0 +1,164 @@
+use std::sync::{Condvar, Mutex};
+use tauri::State;
+
+use crate::decode::SourceHeader;
+use crate::{ErrorCode, ProcessError};
+
+const DEFAULT_BUDGET_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+// Extra bytes per pixel on top of the decoded image: an RGBA8 copy for the
+// encoders plus resize and encoder scratch buffers
+const WORKING_BYTES_PER_PIXEL: u64 = 8;
+
+struct BudgetState {
+    capacity: u64,
+    used: u64,
+}
+
+// Shared memory budget for all images being processed at once (batches and
+// watchers). Work that doesn't fit waits until earlier images finish.
+pub struct MemoryBudget {
+    state: Mutex<BudgetState>,
+    released: Condvar,
+}
+
+// Returns its bytes to the budget when dropped
+pub struct MemoryReservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        MemoryBudget::new(DEFAULT_BUDGET_BYTES)
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(capacity: u64) -> Self {
+        MemoryBudget {
+            state: Mutex::new(BudgetState { capacity, used: 0 }),
+            released: Condvar::new(),
+        }
+    }
+
+    // Rough peak memory needed to process an image with this header
+    pub fn estimate(header: &SourceHeader) -> u64 {
+        header.decoded_bytes() + header.pixels() * WORKING_BYTES_PER_PIXEL
+    }
+
+    // Block until `bytes` fit in the budget. Fails immediately if they could
+    // never fit, rather than waiting forever.
+    pub fn reserve(&self, bytes: u64) -> Result<MemoryReservation<'_>, ProcessError> {
+        let mut state = self.state.lock()
+            .map_err(|_| "Memory budget lock poisoned".to_string())?;
+
+        loop {
+            if bytes > state.capacity {
+                return Err(ProcessError::new(
+                    ErrorCode::MemoryBudgetExceeded,
+                    format!(
+                        "Processing needs about {} MiB, above the memory budget of {} MiB",
+                        bytes / (1024 * 1024),
+                        state.capacity / (1024 * 1024),
+                    ),
+                ));
+            }
+            if state.used + bytes <= state.capacity {
+                state.used += bytes;
+                return Ok(MemoryReservation { budget: self, bytes });
+            }
+            state = self.released.wait(state)
+                .map_err(|_| "Memory budget lock poisoned".to_string())?;
+        }
+    }
+
+    fn set_capacity(&self, capacity: u64) -> Result<(), String> {
+        let mut state = self.state.lock()
+            .map_err(|_| "Memory budget lock poisoned".to_string())?;
+        state.capacity = capacity;
+        // Waiters re-check, and fail if they no longer fit at all
+        self.released.notify_all();
+        Ok(())
+    }
+}
+
+impl Drop for MemoryReservation<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.budget.state.lock() {
+            state.used = state.used.saturating_sub(self.bytes);
+            self.budget.released.notify_all();
+        }
+    }
+}
+
+#[tauri::command]
+pub fn set_memory_budget(bytes: u64, budget: State<MemoryBudget>) -> Result<String, String> {
+    if bytes == 0 {
+        return Err("Memory budget must be greater than zero".to_string());
+    }
+    budget.set_capacity(bytes)?;
+    Ok(format!("Memory budget set to {} MiB", bytes / (1024 * 1024)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn request_larger_than_the_budget_fails_instead_of_blocking() {
+        let budget = MemoryBudget::new(1000);
+        let error = budget.reserve(1001).err().unwrap();
+        assert!(matches!(error.code, Some(ErrorCode::MemoryBudgetExceeded)));
+
+        // Also while other work holds part of the budget
+        let _held = budget.reserve(600).unwrap();
+        assert!(budget.reserve(1001).is_err());
+    }
+
+    #[test]
+    fn reservation_is_released_on_drop() {
+        let budget = MemoryBudget::new(1000);
+        let first = budget.reserve(1000).unwrap();
+        drop(first);
+        let _second = budget.reserve(1000).unwrap();
+        assert_eq!(budget.state.lock().unwrap().used, 1000);
+    }
+
+    #[test]
+    fn waiting_reservation_proceeds_once_memory_is_released() {
+        let budget = MemoryBudget::new(1000);
+        let held = budget.reserve(800).unwrap();
+
+        thread::scope(|scope| {
+            let (done, finished) = mpsc::channel();
+            let budget = &budget;
+            scope.spawn(move || {
+                let reservation = budget.reserve(500).map(|r| r.bytes);
+                done.send(reservation.is_ok()).unwrap();
+            });
+
+            // Still waiting while 800 of 1000 bytes are held
+            assert!(finished.recv_timeout(Duration::from_millis(100)).is_err());
+            drop(held);
+            assert!(finished.recv_timeout(Duration::from_secs(5)).unwrap());
+        });
+        assert_eq!(budget.state.lock().unwrap().used, 0);
+    }
+
+    #[test]
+    fn shrinking_the_budget_fails_waiters_that_no_longer_fit() {
+        let budget = MemoryBudget::new(1000);
+        let _held = budget.reserve(800).unwrap();
+
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| budget.reserve(500).map(|r| r.bytes));
+            thread::sleep(Duration::from_millis(50));
+            budget.set_capacity(400).unwrap();
+            let error = waiter.join().unwrap().err().unwrap();
+            assert!(matches!(error.code, Some(ErrorCode::MemoryBudgetExceeded)));
+        });
+    }
+}
@@ -0,0 +1,385 @@
+use image::{DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Cursor;
+use std::num::NonZeroU8;
+use std::path::Path;
+
+use crate::reduce::{self, ColorAnalysis, IndexedImage};
+
+// Quantized output has always been squeezed as hard as possible; lossless
+// recompression of arbitrary sources defaults to oxipng's own default level
+const QUANTIZED_DEFAULT_LEVEL: u8 = 6;
+const LOSSLESS_DEFAULT_LEVEL: u8 = 2;
+const DEFAULT_ZOPFLI_ITERATIONS: u8 = 15;
+const DEFAULT_QUANTIZE_SPEED: u8 = 5;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum PngDeflater {
+    #[default]
+    #[serde(rename = "libdeflate")]
+    Libdeflate, // Fast
+    #[serde(rename = "zopfli")]
+    Zopfli, // A few percent smaller, much slower
+}
+
+// Ancillary chunks removed by oxipng
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum PngStrip {
+    #[serde(rename = "none")]
+    None, // Keep all chunks
+    #[default]
+    #[serde(rename = "safe")]
+    Safe, // Remove chunks that don't affect how the image is displayed
+    #[serde(rename = "all")]
+    All, // Remove all ancillary chunks, including color profiles
+}
+
+// pngquant (imagequant) settings for lossy PNG output
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuantizeOptions {
+    pub speed: Option<u8>, // 1 (best, slowest) - 10 (fastest), default 5
+    pub dithering: Option<f32>, // 0.0 (off, best for pixel art) - 1.0, default 1.0
+    pub min_quality: Option<u8>, // 0 - 100, below this the palette is rejected, default 0
+    pub max_colors: Option<u32>, // 2 - 256, default 256
+    pub fallback_lossless: Option<bool>, // Write lossless truecolor when min_quality can't be met, default true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PngOptions {
+    pub lossless: Option<bool>, // Optimize mode: keep every pixel and only recompress, default false
+    pub level: Option<u8>, // oxipng effort 0 - 6, default 6 after quantization, 2 otherwise
+    pub deflater: Option<PngDeflater>, // Default libdeflate
+    pub zopfli_iterations: Option<u8>, // Default 15
+    pub interlace: Option<bool>, // Adam7 interlacing; default keeps the source's interlacing
+    pub strip: Option<PngStrip>, // Default safe
+    pub quantize: Option<QuantizeOptions>, // Palette quantization in optimizing modes
+}
+
+fn oxipng_options(options: &PngOptions, default_level: u8) -> oxipng::Options {
+    let mut oxipng_options = oxipng::Options::from_preset(options.level.unwrap_or(default_level).min(6));
+
+    if let Some(PngDeflater::Zopfli) = options.deflater {
+        let iterations = options.zopfli_iterations.unwrap_or(DEFAULT_ZOPFLI_ITERATIONS);
+        oxipng_options.deflate = oxipng::Deflaters::Zopfli {
+            iterations: NonZeroU8::new(iterations).unwrap_or(NonZeroU8::MIN),
+        };
+    }
+    oxipng_options.interlace = options.interlace.map(|interlace| {
+        if interlace {
+            oxipng::Interlacing::Adam7
+        } else {
+            oxipng::Interlacing::None
+        }
+    });
+    oxipng_options.strip = match options.strip.unwrap_or_default() {
+        PngStrip::None => oxipng::StripChunks::None,
+        PngStrip::Safe => oxipng::StripChunks::Safe,
+        PngStrip::All => oxipng::StripChunks::All,
+    };
+    oxipng_options
+}
+
+// Recompress existing PNG data without changing a single pixel
+pub fn optimize_png(data: &[u8], options: &PngOptions) -> Result<Vec<u8>, String> {
+    oxipng::optimize_from_memory(data, &oxipng_options(options, LOSSLESS_DEFAULT_LEVEL))
+        .map_err(|e| format!("Failed to optimize PNG: {}", e))
+}
+
+fn encode_indexed_png(indexed: &IndexedImage, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(match indexed.bit_depth {
+            1 => png::BitDepth::One,
+            2 => png::BitDepth::Two,
+            4 => png::BitDepth::Four,
+            _ => png::BitDepth::Eight,
+        });
+
+        let palette_rgb: Vec<u8> = indexed.palette.iter()
+            .flat_map(|[r, g, b, _]| [*r, *g, *b])
+            .collect();
+        encoder.set_palette(palette_rgb);
+        if indexed.palette.iter().any(|color| color[3] < 255) {
+            let trns: Vec<u8> = indexed.palette.iter().map(|color| color[3]).collect();
+            encoder.set_trns(trns);
+        }
+
+        let mut writer = encoder.write_header()
+            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+        writer.write_image_data(&reduce::pack_rows(indexed, width))
+            .map_err(|e| format!("Failed to write PNG data: {}", e))?;
+    }
+    Ok(png_data)
+}
+
+// Encode without losing anything, in the smallest layout the analysis allows:
+// a 1, 2, 4 or 8-bit palette for images with few colors, otherwise the image's
+// own (already reduced) color type. oxipng then recompresses the result.
+pub fn encode_png_lossless(
+    img: &DynamicImage,
+    options: &PngOptions,
+    analysis: Option<&ColorAnalysis>,
+) -> Result<Vec<u8>, String> {
+    // Up to 8-bit grayscale is already as small as a palette, minus the PLTE chunk
+    let palette = analysis
+        .and_then(|analysis| analysis.palette.as_deref().filter(|palette| {
+            !(analysis.grayscale && analysis.opaque && palette.len() > 16)
+        }));
+
+    let png_data = match palette {
+        Some(palette) => {
+            let (width, height) = img.dimensions();
+            encode_indexed_png(&reduce::to_indexed(img, palette), width, height)?
+        }
+        None => {
+            let mut png_data = Vec::new();
+            img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)
+                .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+            png_data
+        }
+    };
+
+    optimize_png(&png_data, options)
+}
+
+// Plain 8-bit RGBA, recompressed without any color type or bit depth
+// reduction, for containers that require it (PNG entries in ICO files)
+pub fn encode_png_rgba(img: &RgbaImage, options: &PngOptions) -> Result<Vec<u8>, String> {
+    let mut png_data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png_data), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+
+    let mut oxipng_options = oxipng_options(options, LOSSLESS_DEFAULT_LEVEL);
+    oxipng_options.bit_depth_reduction = false;
+    oxipng_options.color_type_reduction = false;
+    oxipng_options.palette_reduction = false;
+    oxipng_options.grayscale_reduction = false;
+    oxipng_options.interlace = Some(oxipng::Interlacing::None);
+    oxipng::optimize_from_memory(&png_data, &oxipng_options)
+        .map_err(|e| format!("Failed to optimize PNG: {}", e))
+}
+
+pub fn save_png_lossless(
+    img: &DynamicImage,
+    output_path: &Path,
+    options: &PngOptions,
+    analysis: Option<&ColorAnalysis>,
+) -> Result<(), String> {
+    let png_data = encode_png_lossless(img, options, analysis)?;
+    fs::write(output_path, png_data)
+        .map_err(|e| format!("Failed to write PNG file: {}", e))
+}
+
+// Returns a warning when the palette couldn't reach min_quality and the image
+// was written losslessly instead
+pub fn save_png_quantized(
+    img: &DynamicImage,
+    output_path: &Path,
+    quality: u8,
+    options: &PngOptions,
+    analysis: Option<&ColorAnalysis>,
+) -> Result<Option<String>, String> {
+    // Use pngquant algorithm (imagequant) for lossy compression with quality control
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    
+    // Convert ImageBuffer to RGBA format for imagequant
+    let rgba_data: Vec<imagequant::RGBA> = rgba.pixels()
+        .map(|p| imagequant::RGBA::new(p[0], p[1], p[2], p[3]))
+        .collect();
+    
+    let quantize = options.quantize.clone().unwrap_or_default();
+
+    // Create attributes for pngquant
+    let mut liq = imagequant::new();
+    
+    // Set quality range (min quality, max quality)
+    // Higher quality = more colors preserved
+    let min_quality = quantize.min_quality.unwrap_or(0).min(quality);
+    liq.set_quality(min_quality, quality)
+        .map_err(|e| format!("Failed to set quality: {:?}", e))?;
+    
+    // Speed vs quality tradeoff (1-10, 1=best quality/slowest, 10=worst/fastest)
+    let speed = quantize.speed.unwrap_or(DEFAULT_QUANTIZE_SPEED).clamp(1, 10);
+    liq.set_speed(speed as i32)
+        .map_err(|e| format!("Failed to set speed: {:?}", e))?;
+
+    if let Some(max_colors) = quantize.max_colors {
+        liq.set_max_colors(max_colors.clamp(2, 256))
+            .map_err(|e| format!("Failed to set palette size: {:?}", e))?;
+    }
+    
+    // Create image for quantization
+    let mut img_data = liq.new_image(
+        rgba_data.into_boxed_slice(),
+        width as usize,
+        height as usize,
+        0.0
+    ).map_err(|e| format!("Failed to create image: {:?}", e))?;
+    
+    // Quantize (reduce colors). A palette that can't reach min_quality would
+    // band visibly, so keep full color instead unless that's disabled.
+    let mut result = match liq.quantize(&mut img_data) {
+        Ok(result) => result,
+        Err(imagequant::Error::QualityTooLow) if quantize.fallback_lossless.unwrap_or(true) => {
+            save_png_lossless(img, output_path, options, analysis)?;
+            return Ok(Some(format!(
+                "Palette quality was below min_quality {}; wrote a lossless PNG instead",
+                min_quality
+            )));
+        }
+        Err(e) => return Err(format!("Failed to quantize: {:?}", e)),
+    };
+    
+    // Set dithering level (0.0 - 1.0)
+    result.set_dithering_level(quantize.dithering.unwrap_or(1.0).clamp(0.0, 1.0))
+        .map_err(|e| format!("Failed to set dithering: {:?}", e))?;
+    
+    // Get quantized image data
+    let (palette, pixels) = result.remapped(&mut img_data)
+        .map_err(|e| format!("Failed to remap: {:?}", e))?;
+    
+    // Write PNG with oxipng optimization
+    write_optimized_png(output_path, &pixels, &palette, width, height, options)?;
+    
+    Ok(None)
+}
+
+fn write_optimized_png(
+    output_path: &Path,
+    pixels: &[u8],
+    palette: &[imagequant::RGBA],
+    width: u32,
+    height: u32,
+    options: &PngOptions,
+) -> Result<(), String> {
+    use std::io::BufWriter;
+    
+    // First write to a temporary buffer
+    let mut png_data = Vec::new();
+    {
+        let writer = BufWriter::new(&mut png_data);
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        
+        // Convert palette to PNG format
+        let palette_rgb: Vec<u8> = palette.iter()
+            .flat_map(|c| [c.r, c.g, c.b])
+            .collect();
+        encoder.set_palette(palette_rgb);
+        
+        // Set transparency if needed
+        let has_alpha = palette.iter().any(|c| c.a < 255);
+        if has_alpha {
+            let trns: Vec<u8> = palette.iter().map(|c| c.a).collect();
+            encoder.set_trns(trns);
+        }
+        
+        let mut writer = encoder.write_header()
+            .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+        writer.write_image_data(pixels)
+            .map_err(|e| format!("Failed to write PNG data: {}", e))?;
+    }
+    
+    // Optimize with oxipng; the palette is already lossy, so invisible pixels may change too
+    let optimize_options = oxipng::Options {
+        optimize_alpha: true,
+        ..oxipng_options(options, QUANTIZED_DEFAULT_LEVEL)
+    };
+    
+    let optimized = oxipng::optimize_from_memory(&png_data, &optimize_options)
+        .map_err(|e| format!("Failed to optimize PNG: {}", e))?;
+    
+    fs::write(output_path, optimized)
+        .map_err(|e| format!("Failed to write optimized PNG: {}", e))?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // Smooth two-axis gradient; far too many colors for a tiny palette
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+            Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8])
+        }))
+    }
+
+    fn strict_palette(fallback_lossless: Option<bool>) -> PngOptions {
+        PngOptions {
+            level: Some(1),
+            quantize: Some(QuantizeOptions {
+                min_quality: Some(95),
+                max_colors: Some(2),
+                fallback_lossless,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn temp_png(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("optisnap-png-{}-{}.png", name, std::process::id()))
+    }
+
+    #[test]
+    fn quality_too_low_falls_back_to_lossless_with_a_warning() {
+        let img = gradient();
+        let path = temp_png("fallback");
+
+        let warning = save_png_quantized(&img, &path, 100, &strict_palette(None), None).unwrap();
+        let decoded = image::open(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert!(warning.unwrap().contains("min_quality 95"));
+        assert_eq!(decoded.to_rgb8(), img.to_rgb8());
+    }
+
+    #[test]
+    fn quality_too_low_is_an_error_without_fallback() {
+        let path = temp_png("no-fallback");
+        let result = save_png_quantized(&gradient(), &path, 100, &strict_palette(Some(false)), None);
+        fs::remove_file(&path).ok();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fallback_uses_the_color_analysis() {
+        // Eight colors: too many for a two-color palette, few enough for a small one
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, _| {
+            let level = (x / 8) as u8;
+            Rgb([level * 32, 255 - level * 32, (level % 2) * 255])
+        }));
+        let analysis = reduce::analyze(&img);
+        let path = temp_png("analysis");
+
+        let warning = save_png_quantized(&img, &path, 100, &strict_palette(None), Some(&analysis)).unwrap();
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert!(warning.is_some());
+        // IHDR color type 3: the lossless fallback still writes a palette
+        assert_eq!(data[25], 3);
+        assert_eq!(image::load_from_memory(&data).unwrap().to_rgb8(), img.to_rgb8());
+    }
+
+    #[test]
+    fn reachable_quality_writes_a_palette_without_warning() {
+        let img = gradient();
+        let path = temp_png("palette");
+
+        let warning = save_png_quantized(&img, &path, 80, &PngOptions::default(), None).unwrap();
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert!(warning.is_none());
+        assert_eq!(data[25], 3);
+    }
+}
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::{OperationMode, OutputFormat, ProcessingSettings, ResizeMode};
+
+// A reusable request template: everything in `OptimizeBatchRequest` except
+// which files are processed and where they go
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub builtin: bool, // Built-in presets cannot be updated or deleted
+    #[serde(flatten)]
+    pub settings: ProcessingSettings,
+}
+
+// On-disk layout for both the store and import/export files. TOML needs a
+// table at the top level, so presets live under a `presets` key.
+#[derive(Debug, Serialize, Deserialize)]
+struct PresetFile {
+    presets: Vec<Preset>,
+}
+
+enum PresetFormat {
+    Json,
+    Toml,
+}
+
+impl PresetFormat {
+    fn from_path(path: &Path) -> PresetFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => PresetFormat::Toml,
+            _ => PresetFormat::Json,
+        }
+    }
+}
+
+fn builtin_presets() -> Vec<Preset> {
+    vec![
+        Preset {
+            name: "Web hero".to_string(),
+            description: Some("Full-width banner images as WebP, at most 1920×1080".to_string()),
+            builtin: true,
+            settings: ProcessingSettings {
+                format: Some(OutputFormat::Webp),
+                operation_mode: OperationMode::All,
+                quality: Some(80.0),
+                resize_mode: Some(ResizeMode::Dimensions),
+                max_width: Some(1920),
+                max_height: Some(1080),
+                keep_aspect_ratio: Some(true),
+                ..Default::default()
+            },
+        },
+        Preset {
+            name: "Thumbnail".to_string(),
+            description: Some("Small WebP previews that fit within 320×320".to_string()),
+            builtin: true,
+            settings: ProcessingSettings {
+                format: Some(OutputFormat::Webp),
+                operation_mode: OperationMode::All,
+                quality: Some(70.0),
+                resize_mode: Some(ResizeMode::Dimensions),
+                max_width: Some(320),
+                max_height: Some(320),
+                keep_aspect_ratio: Some(true),
+                ..Default::default()
+            },
+        },
+        Preset {
+            name: "Archive lossless".to_string(),
+            description: Some("Lossless PNG copies at the original size".to_string()),
+            builtin: true,
+            settings: ProcessingSettings {
+                format: Some(OutputFormat::Png),
+                operation_mode: OperationMode::Convert,
+                ..Default::default()
+            },
+        },
+    ]
+}
+
+fn parse_presets(data: &str, format: &PresetFormat) -> Result<Vec<Preset>, String> {
+    let file: PresetFile = match format {
+        PresetFormat::Json => serde_json::from_str(data)
+            .map_err(|e| format!("Invalid preset JSON: {}", e))?,
+        PresetFormat::Toml => toml::from_str(data)
+            .map_err(|e| format!("Invalid preset TOML: {}", e))?,
+    };
+    Ok(file.presets)
+}
+
+// TOML has no null. Unset settings can simply be left out, since an absent
+// key reads back as unset; see `check_toml_patches` for rule patches.
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, item| !item.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => {
+            items.retain(|item| !item.is_null());
+            items.iter_mut().for_each(strip_nulls);
+        }
+        _ => {}
+    }
+}
+
+fn contains_null(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::Object(map) => map.values().any(contains_null),
+        serde_json::Value::Array(items) => items.iter().any(contains_null),
+        _ => false,
+    }
+}
+
+// In a rule patch, null clears a setting while an absent key keeps the base
+// one, so dropping nulls would change what the preset does
+fn check_toml_patches(presets: &[Preset]) -> Result<(), String> {
+    for preset in presets {
+        let rules = preset.settings.rules.iter().flatten();
+        if rules.flat_map(|rule| rule.settings.values()).any(contains_null) {
+            return Err(format!(
+                "Preset \"{}\" has a rule that clears a setting with null, which TOML cannot store; export it as JSON instead",
+                preset.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn serialize_presets(presets: Vec<Preset>, format: &PresetFormat) -> Result<String, String> {
+    let file = PresetFile { presets };
+    match format {
+        PresetFormat::Json => serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize presets: {}", e)),
+        PresetFormat::Toml => {
+            check_toml_patches(&file.presets)?;
+            let mut value = serde_json::to_value(&file)
+                .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+            strip_nulls(&mut value);
+            toml::to_string_pretty(&value)
+                .map_err(|e| format!("Failed to serialize presets: {}", e))
+        }
+    }
+}
+
+// User presets, persisted as JSON in the app data directory. Built-in presets
+// are never written to disk.
+pub struct PresetStore {
+    path: PathBuf,
+    presets: Mutex<Vec<Preset>>,
+}
+
+impl PresetStore {
+    pub fn load(path: PathBuf) -> Self {
+        let presets = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| parse_presets(&data, &PresetFormat::Json).ok())
+            .unwrap_or_default();
+
+        PresetStore {
+            path,
+            presets: Mutex::new(presets),
+        }
+    }
+
+    fn save(&self, presets: &[Preset]) -> Result<(), String> {
+        let data = serialize_presets(presets.to_vec(), &PresetFormat::Json)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create preset directory: {}", e))?;
+        }
+        fs::write(&self.path, data)
+            .map_err(|e| format!("Failed to write presets: {}", e))?;
+
+        Ok(())
+    }
+
+    fn all(&self) -> Result<Vec<Preset>, String> {
+        let presets = self.presets.lock()
+            .map_err(|_| "Preset store lock poisoned".to_string())?;
+
+        let mut all = builtin_presets();
+        all.extend(presets.iter().cloned());
+        Ok(all)
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name cannot be empty".to_string());
+    }
+    if builtin_presets().iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+        return Err(format!("\"{}\" is a built-in preset", name));
+    }
+    Ok(name.to_string())
+}
+
+#[tauri::command]
+pub fn list_presets(store: State<PresetStore>) -> Result<Vec<Preset>, String> {
+    store.all()
+}
+
+#[tauri::command]
+pub fn create_preset(mut preset: Preset, store: State<PresetStore>) -> Result<Preset, String> {
+    preset.name = validate_name(&preset.name)?;
+    preset.builtin = false;
+
+    let mut presets = store.presets.lock()
+        .map_err(|_| "Preset store lock poisoned".to_string())?;
+    if presets.iter().any(|p| p.name.eq_ignore_ascii_case(&preset.name)) {
+        return Err(format!("A preset named \"{}\" already exists", preset.name));
+    }
+
+    presets.push(preset.clone());
+    store.save(&presets)?;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub fn update_preset(name: String, mut preset: Preset, store: State<PresetStore>) -> Result<Preset, String> {
+    validate_name(&name)?;
+    preset.name = validate_name(&preset.name)?;
+    preset.builtin = false;
+
+    let mut presets = store.presets.lock()
+        .map_err(|_| "Preset store lock poisoned".to_string())?;
+
+    // Renaming onto another existing preset would silently create a duplicate
+    let renamed_onto_other = !preset.name.eq_ignore_ascii_case(&name)
+        && presets.iter().any(|p| p.name.eq_ignore_ascii_case(&preset.name));
+    if renamed_onto_other {
+        return Err(format!("A preset named \"{}\" already exists", preset.name));
+    }
+
+    let existing = presets.iter_mut()
+        .find(|p| p.name.eq_ignore_ascii_case(&name))
+        .ok_or_else(|| format!("Preset not found: {}", name))?;
+    *existing = preset.clone();
+
+    store.save(&presets)?;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub fn delete_preset(name: String, store: State<PresetStore>) -> Result<String, String> {
+    validate_name(&name)?;
+
+    let mut presets = store.presets.lock()
+        .map_err(|_| "Preset store lock poisoned".to_string())?;
+    let count = presets.len();
+    presets.retain(|p| !p.name.eq_ignore_ascii_case(&name));
+    if presets.len() == count {
+        return Err(format!("Preset not found: {}", name));
+    }
+
+    store.save(&presets)?;
+    Ok(format!("Deleted preset: {}", name))
+}
+
+// Export to a `.json` or `.toml` file (chosen by extension). Exports every
+// preset, built-ins included, unless specific names are given.
+#[tauri::command]
+pub fn export_presets(
+    path: String,
+    names: Option<Vec<String>>,
+    store: State<PresetStore>,
+) -> Result<usize, String> {
+    let output = Path::new(&path);
+    let presets: Vec<Preset> = store.all()?
+        .into_iter()
+        .filter(|p| match &names {
+            Some(names) => names.iter().any(|n| n.eq_ignore_ascii_case(&p.name)),
+            None => true,
+        })
+        .collect();
+    let count = presets.len();
+
+    let data = serialize_presets(presets, &PresetFormat::from_path(output))?;
+    fs::write(output, data)
+        .map_err(|e| format!("Failed to write preset file: {}", e))?;
+
+    Ok(count)
+}
+
+// Import from a `.json` or `.toml` file. Built-in entries in the file are
+// skipped; existing user presets with the same name are only replaced when
+// `overwrite` is set.
+#[tauri::command]
+pub fn import_presets(
+    path: String,
+    overwrite: Option<bool>,
+    store: State<PresetStore>,
+) -> Result<Vec<Preset>, String> {
+    let input = Path::new(&path);
+    let data = fs::read_to_string(input)
+        .map_err(|e| format!("Failed to read preset file: {}", e))?;
+    let incoming = parse_presets(&data, &PresetFormat::from_path(input))?;
+    let overwrite = overwrite.unwrap_or(false);
+
+    let mut presets = store.presets.lock()
+        .map_err(|_| "Preset store lock poisoned".to_string())?;
+    let mut imported = Vec::new();
+
+    for mut preset in incoming {
+        let name = match validate_name(&preset.name) {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        preset.name = name;
+        preset.builtin = false;
+
+        match presets.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&preset.name)) {
+            Some(existing) if overwrite => *existing = preset.clone(),
+            Some(_) => continue,
+            None => presets.push(preset.clone()),
+        }
+        imported.push(preset);
+    }
+
+    store.save(&presets)?;
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Presets don't implement PartialEq; compare their wire form instead
+    fn as_json(presets: &[Preset]) -> serde_json::Value {
+        serde_json::to_value(presets).unwrap()
+    }
+
+    fn preset_with_rules() -> Preset {
+        let rules = json!([
+            { "match": { "glob": "*.png", "has_alpha": true }, "settings": { "format": "webp", "quality": 90.0 } },
+            { "match": { "formats": ["heic"], "min_width": 4000 }, "settings": { "max_width": 2000, "jpeg": { "progressive": true } } },
+        ]);
+        Preset {
+            name: "Product shots".to_string(),
+            description: None,
+            builtin: false,
+            settings: ProcessingSettings {
+                format: Some(OutputFormat::Jpeg),
+                quality: Some(82.5),
+                rules: Some(serde_json::from_value(rules).unwrap()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn round_trip(presets: Vec<Preset>, format: PresetFormat) -> Vec<Preset> {
+        let data = serialize_presets(presets, &format).unwrap();
+        parse_presets(&data, &format).unwrap()
+    }
+
+    #[test]
+    fn json_round_trip_keeps_every_setting() {
+        let mut presets = builtin_presets();
+        presets.push(preset_with_rules());
+        let imported = round_trip(presets.clone(), PresetFormat::Json);
+        assert_eq!(as_json(&imported), as_json(&presets));
+    }
+
+    #[test]
+    fn toml_round_trip_keeps_every_setting() {
+        let imported = round_trip(builtin_presets(), PresetFormat::Toml);
+        assert_eq!(as_json(&imported), as_json(&builtin_presets()));
+    }
+
+    #[test]
+    fn toml_round_trip_keeps_rules() {
+        let imported = round_trip(vec![preset_with_rules()], PresetFormat::Toml);
+        assert_eq!(as_json(&imported), as_json(&[preset_with_rules()]));
+    }
+
+    #[test]
+    fn toml_export_refuses_null_patch_values() {
+        let mut preset = preset_with_rules();
+        let rules = preset.settings.rules.as_mut().unwrap();
+        rules[1].settings.insert("sharpen".to_string(), serde_json::Value::Null);
+
+        let error = serialize_presets(vec![preset.clone()], &PresetFormat::Toml).unwrap_err();
+        assert!(error.contains("Product shots") && error.contains("JSON"), "{}", error);
+        // JSON keeps the null, so it still round trips exactly
+        let imported = round_trip(vec![preset.clone()], PresetFormat::Json);
+        assert_eq!(as_json(&imported), as_json(&[preset]));
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert!(matches!(PresetFormat::from_path(Path::new("a.TOML")), PresetFormat::Toml));
+        assert!(matches!(PresetFormat::from_path(Path::new("a.json")), PresetFormat::Json));
+        assert!(matches!(PresetFormat::from_path(Path::new("presets")), PresetFormat::Json));
+    }
+}
@@ -0,0 +1,198 @@
+use image::{DynamicImage, GenericImageView};
+use std::collections::{HashMap, HashSet};
+
+// Palettes only pay off up to this many colors
+const MAX_PALETTE_COLORS: usize = 256;
+
+// What an image actually uses, found by scanning every pixel
+#[derive(Debug, Clone)]
+pub struct ColorAnalysis {
+    pub opaque: bool, // Alpha channel (if any) is fully opaque everywhere
+    pub grayscale: bool, // Every pixel has equal color channels
+    pub palette: Option<Vec<[u8; 4]>>, // Distinct RGBA colors, for 8-bit images with at most 256
+}
+
+// Palette image with one index per pixel, at the smallest bit depth that fits
+pub struct IndexedImage {
+    pub palette: Vec<[u8; 4]>,
+    pub indices: Vec<u8>,
+    pub bit_depth: u8, // 1, 2, 4 or 8
+}
+
+// (grayscale, opaque) for interleaved samples
+fn scan_samples<T: Copy + PartialEq>(samples: &[T], channels: usize, opaque_value: T) -> (bool, bool) {
+    let has_color = channels >= 3;
+    let alpha_index = match channels {
+        2 => Some(1),
+        4 => Some(3),
+        _ => None,
+    };
+
+    let mut grayscale = true;
+    let mut opaque = true;
+    for pixel in samples.chunks_exact(channels) {
+        if grayscale && has_color && (pixel[0] != pixel[1] || pixel[1] != pixel[2]) {
+            grayscale = false;
+        }
+        if opaque && alpha_index.is_some_and(|i| pixel[i] != opaque_value) {
+            opaque = false;
+        }
+        if !grayscale && !opaque {
+            break;
+        }
+    }
+    (grayscale, opaque)
+}
+
+fn collect_palette(img: &DynamicImage) -> Option<Vec<[u8; 4]>> {
+    let mut seen = HashSet::new();
+    let mut palette = Vec::new();
+    for (_, _, pixel) in img.pixels() {
+        if seen.insert(pixel.0) {
+            palette.push(pixel.0);
+            if palette.len() > MAX_PALETTE_COLORS {
+                return None;
+            }
+        }
+    }
+    Some(palette)
+}
+
+pub fn analyze(img: &DynamicImage) -> ColorAnalysis {
+    let (grayscale, opaque) = match img {
+        DynamicImage::ImageLuma8(buf) => scan_samples(buf.as_raw(), 1, u8::MAX),
+        DynamicImage::ImageLumaA8(buf) => scan_samples(buf.as_raw(), 2, u8::MAX),
+        DynamicImage::ImageRgb8(buf) => scan_samples(buf.as_raw(), 3, u8::MAX),
+        DynamicImage::ImageRgba8(buf) => scan_samples(buf.as_raw(), 4, u8::MAX),
+        DynamicImage::ImageLuma16(buf) => scan_samples(buf.as_raw(), 1, u16::MAX),
+        DynamicImage::ImageLumaA16(buf) => scan_samples(buf.as_raw(), 2, u16::MAX),
+        DynamicImage::ImageRgb16(buf) => scan_samples(buf.as_raw(), 3, u16::MAX),
+        DynamicImage::ImageRgba16(buf) => scan_samples(buf.as_raw(), 4, u16::MAX),
+        DynamicImage::ImageRgb32F(buf) => scan_samples(buf.as_raw(), 3, 1.0),
+        DynamicImage::ImageRgba32F(buf) => scan_samples(buf.as_raw(), 4, 1.0),
+        _ => (false, false),
+    };
+
+    let eight_bit = img.color().bytes_per_pixel() == img.color().channel_count();
+    ColorAnalysis {
+        opaque,
+        grayscale,
+        palette: if eight_bit { collect_palette(img) } else { None },
+    }
+}
+
+// Drop channels that carry no information: alpha when fully opaque, color
+// when every pixel is gray. Sample depth is kept.
+pub fn reduce_channels(img: DynamicImage, analysis: &ColorAnalysis) -> DynamicImage {
+    let color = img.color();
+    let gray = analysis.grayscale;
+    let opaque = analysis.opaque || !color.has_alpha();
+
+    let reduced = match color.bytes_per_pixel() / color.channel_count() {
+        1 => match (gray, opaque) {
+            (true, true) => DynamicImage::ImageLuma8(img.to_luma8()),
+            (true, false) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+            (false, true) => DynamicImage::ImageRgb8(img.to_rgb8()),
+            (false, false) => return img,
+        },
+        2 => match (gray, opaque) {
+            (true, true) => DynamicImage::ImageLuma16(img.to_luma16()),
+            (true, false) => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+            (false, true) => DynamicImage::ImageRgb16(img.to_rgb16()),
+            (false, false) => return img,
+        },
+        // There is no floating point grayscale type
+        _ if opaque => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+        _ => return img,
+    };
+
+    if reduced.color() == color {
+        img
+    } else {
+        reduced
+    }
+}
+
+pub fn palette_bit_depth(colors: usize) -> u8 {
+    match colors {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    }
+}
+
+pub fn to_indexed(img: &DynamicImage, palette: &[[u8; 4]]) -> IndexedImage {
+    let lookup: HashMap<[u8; 4], u8> = palette.iter()
+        .enumerate()
+        .map(|(index, color)| (*color, index as u8))
+        .collect();
+    let indices = img.pixels()
+        .map(|(_, _, pixel)| lookup.get(&pixel.0).copied().unwrap_or(0))
+        .collect();
+
+    IndexedImage {
+        palette: palette.to_vec(),
+        indices,
+        bit_depth: palette_bit_depth(palette.len()),
+    }
+}
+
+// Pack indices into rows of `bit_depth` bits each, most significant bits
+// first, with every row starting on a byte boundary
+pub fn pack_rows(indexed: &IndexedImage, width: u32) -> Vec<u8> {
+    let bit_depth = indexed.bit_depth as usize;
+    if bit_depth == 8 {
+        return indexed.indices.clone();
+    }
+
+    let per_byte = 8 / bit_depth;
+    let mut packed = Vec::new();
+    for row in indexed.indices.chunks(width as usize) {
+        for group in row.chunks(per_byte) {
+            let mut byte = 0u8;
+            for (i, &index) in group.iter().enumerate() {
+                byte |= index << (8 - bit_depth * (i + 1));
+            }
+            packed.push(byte);
+        }
+    }
+    packed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed(indices: Vec<u8>, bit_depth: u8) -> IndexedImage {
+        IndexedImage {
+            palette: Vec::new(),
+            indices,
+            bit_depth,
+        }
+    }
+
+    #[test]
+    fn pack_rows_starts_each_row_on_a_byte() {
+        // Width 3: each row fits in one byte, with the low bits left zero
+        let one_bit = indexed(vec![1, 0, 1, 0, 1, 1], 1);
+        assert_eq!(pack_rows(&one_bit, 3), vec![0b1010_0000, 0b0110_0000]);
+
+        let two_bit = indexed(vec![3, 2, 1, 0, 1, 2], 2);
+        assert_eq!(pack_rows(&two_bit, 3), vec![0b1110_0100, 0b0001_1000]);
+
+        let four_bit = indexed(vec![0xA, 0xB, 0xC, 0x1, 0x2, 0x3], 4);
+        assert_eq!(pack_rows(&four_bit, 3), vec![0xAB, 0xC0, 0x12, 0x30]);
+
+        let eight_bit = indexed(vec![200, 1, 2, 3, 4, 5], 8);
+        assert_eq!(pack_rows(&eight_bit, 3), vec![200, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn palette_depth_fits_the_color_count() {
+        assert_eq!(palette_bit_depth(2), 1);
+        assert_eq!(palette_bit_depth(4), 2);
+        assert_eq!(palette_bit_depth(16), 4);
+        assert_eq!(palette_bit_depth(17), 8);
+    }
+}
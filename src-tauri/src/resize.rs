@@ -0,0 +1,795 @@
+use fast_image_resize as fir;
+use image::imageops::{self, FilterType};
+use image::{ColorType, DynamicImage, GenericImageView, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::depth::SampleDepth;
+use crate::smartcrop::{self, FocalPoint};
+use crate::{ProcessingSettings, ResizeMode};
+
+const DEFAULT_BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ResizeFilter {
+    #[serde(rename = "nearest")]
+    Nearest, // Fastest, blocky; good for pixel art
+    #[serde(rename = "triangle")]
+    Triangle, // Bilinear; fast for large downscales
+    #[serde(rename = "catmull_rom")]
+    CatmullRom, // Bicubic; sharp with little ringing
+    #[serde(rename = "gaussian")]
+    Gaussian, // Soft, no ringing
+    #[default]
+    #[serde(rename = "lanczos3")]
+    Lanczos3, // Sharpest, slowest
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ResizeBackend {
+    #[default]
+    #[serde(rename = "standard")]
+    Standard, // image crate resampling
+    #[serde(rename = "fast")]
+    Fast, // SIMD, multi-threaded convolution plus JPEG shrink-on-load
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+
+    fn fast_algorithm(self) -> fir::ResizeAlg {
+        match self {
+            ResizeFilter::Nearest => fir::ResizeAlg::Nearest,
+            ResizeFilter::Triangle => fir::ResizeAlg::Convolution(fir::FilterType::Bilinear),
+            ResizeFilter::CatmullRom => fir::ResizeAlg::Convolution(fir::FilterType::CatmullRom),
+            ResizeFilter::Gaussian => fir::ResizeAlg::Convolution(fir::FilterType::Gaussian),
+            ResizeFilter::Lanczos3 => fir::ResizeAlg::Convolution(fir::FilterType::Lanczos3),
+        }
+    }
+}
+
+// Filter, backend and sharpening, chosen once per file
+#[derive(Debug, Clone, Copy)]
+struct Resampler {
+    filter: ResizeFilter,
+    backend: ResizeBackend,
+    sharpen: Option<SharpenOptions>, // Only set when the plan scales the source down
+}
+
+impl Resampler {
+    fn for_plan(plan: &ResizePlan, source: (u32, u32), settings: &ProcessingSettings) -> Self {
+        // Sharpening only compensates for the softening of a downscale
+        let downscaled = plan.scale(source).is_some_and(|scale| scale < 1.0);
+        Resampler {
+            filter: settings.resize_filter.unwrap_or_default(),
+            backend: settings.resize_backend.unwrap_or_default(),
+            sharpen: settings.sharpen.filter(|_| downscaled),
+        }
+    }
+
+    // Resize, then sharpen. Callers crop or pad afterwards so the mask never
+    // sees the canvas border.
+    fn resample(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        self.sharpened(self.resize_exact(img, width, height))
+    }
+
+    fn sharpened(&self, img: DynamicImage) -> DynamicImage {
+        match &self.sharpen {
+            Some(options) => unsharp_mask(img, options),
+            None => img,
+        }
+    }
+
+    fn resize_exact(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        if let ResizeBackend::Fast = self.backend {
+            let mut resized = DynamicImage::new(width, height, img.color());
+            let options = fir::ResizeOptions::new().resize_alg(self.filter.fast_algorithm());
+            // Pixel layouts the fast path can't handle fall back to the standard one
+            if fir::Resizer::new().resize(img, &mut resized, &options).is_ok() {
+                return resized;
+            }
+        }
+        img.resize_exact(width, height, self.filter.filter_type())
+    }
+}
+
+// Unsharp mask applied after downscaling
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SharpenOptions {
+    pub amount: f32, // Strength, e.g. 0.5 adds 50% of the detail back
+    pub radius: f32, // Gaussian sigma in pixels
+    pub threshold: Option<u8>, // Minimum difference (0-255) before sharpening, default 0
+}
+
+// Parse "#RGB", "#RRGGBB" or "#RRGGBBAA" (leading '#' optional)
+pub fn parse_hex_color(value: &str) -> Result<Rgba<u8>, String> {
+    let hex = value.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid color: {}", value);
+
+    let channel = |range: std::ops::Range<usize>| {
+        hex.get(range)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or_else(invalid)
+    };
+
+    match hex.len() {
+        3 => {
+            let short = |i: usize| channel(i..i + 1).map(|v| v * 17);
+            Ok(Rgba([short(0)?, short(1)?, short(2)?, 255]))
+        }
+        6 => Ok(Rgba([channel(0..2)?, channel(2..4)?, channel(4..6)?, 255])),
+        8 => Ok(Rgba([channel(0..2)?, channel(2..4)?, channel(4..6)?, channel(6..8)?])),
+        _ => Err(invalid()),
+    }
+}
+
+// Scale so that `(width, height)` has its long/short/one side equal to `length`
+fn scaled_to(width: u32, height: u32, side: u32, length: u32) -> (u32, u32) {
+    let scale = length as f64 / side as f64;
+    let new_width = ((width as f64) * scale).round().max(1.0) as u32;
+    let new_height = ((height as f64) * scale).round().max(1.0) as u32;
+    (new_width, new_height)
+}
+
+// Scale to cover the target box, then crop the overflow. The crop window is
+// centered on the given focal point, or chosen by saliency when `smart` is set.
+fn cover(
+    img: &DynamicImage,
+    target_w: u32,
+    target_h: u32,
+    focal_point: Option<FocalPoint>,
+    smart: bool,
+    resampler: Resampler,
+) -> (DynamicImage, Option<FocalPoint>) {
+    let (width, height) = img.dimensions();
+    let scale = f64::max(
+        target_w as f64 / width as f64,
+        target_h as f64 / height as f64,
+    );
+    let scaled_w = (((width as f64) * scale).round() as u32).max(target_w);
+    let scaled_h = (((height as f64) * scale).round() as u32).max(target_h);
+
+    let scaled = resampler.resample(img, scaled_w, scaled_h);
+    let focal_point = match focal_point {
+        Some(point) => Some(point.clamped()),
+        None if smart => Some(smartcrop::find_focal_point(&scaled, target_w, target_h)),
+        None => None,
+    };
+
+    let (x, y) = match focal_point {
+        Some(point) => smartcrop::crop_origin(point, scaled_w, scaled_h, target_w, target_h),
+        None => ((scaled_w - target_w) / 2, (scaled_h - target_h) / 2),
+    };
+    (scaled.crop_imm(x, y, target_w, target_h), focal_point)
+}
+
+// Scale to fit inside the target box, then pad to exactly the target size
+fn contain(
+    img: &DynamicImage,
+    target_w: u32,
+    target_h: u32,
+    background: Rgba<u8>,
+    resampler: Resampler,
+) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let scale = f64::min(target_w as f64 / width as f64, target_h as f64 / height as f64);
+    let scaled_w = (((width as f64) * scale).round() as u32).clamp(1, target_w);
+    let scaled_h = (((height as f64) * scale).round() as u32).clamp(1, target_h);
+    let scaled = resampler.resample(img, scaled_w, scaled_h);
+
+    let x = ((target_w - scaled_w) / 2) as i64;
+    let y = ((target_h - scaled_h) / 2) as i64;
+
+    // Pad at the source's sample depth so 16-bit and float images keep their precision
+    match SampleDepth::of(img.color()) {
+        SampleDepth::Eight => {
+            let mut canvas = RgbaImage::from_pixel(target_w, target_h, background);
+            imageops::overlay(&mut canvas, &scaled.to_rgba8(), x, y);
+            DynamicImage::ImageRgba8(canvas)
+        }
+        SampleDepth::Sixteen => {
+            let background = Rgba(background.0.map(|v| v as u16 * 257));
+            let mut canvas = ImageBuffer::from_pixel(target_w, target_h, background);
+            imageops::overlay(&mut canvas, &scaled.to_rgba16(), x, y);
+            DynamicImage::ImageRgba16(canvas)
+        }
+        SampleDepth::Float => {
+            let background = Rgba(background.0.map(|v| v as f32 / 255.0));
+            let mut canvas = Rgba32FImage::from_pixel(target_w, target_h, background);
+            imageops::overlay(&mut canvas, &scaled.to_rgba32f(), x, y);
+            DynamicImage::ImageRgba32F(canvas)
+        }
+    }
+}
+
+// Convert back to the color type the image had before an intermediate step
+pub fn to_color_type(img: DynamicImage, color: ColorType) -> DynamicImage {
+    match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(img.to_luma8()),
+        ColorType::La8 => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(img.to_rgb8()),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(img.to_rgba8()),
+        ColorType::L16 => DynamicImage::ImageLuma16(img.to_luma16()),
+        ColorType::La16 => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(img.to_rgb16()),
+        ColorType::Rgba16 => DynamicImage::ImageRgba16(img.to_rgba16()),
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+        _ => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+    }
+}
+
+// out = original + amount * (original - blurred), wherever the difference
+// exceeds the threshold. Works in float so 16-bit sources keep their precision.
+fn unsharp_mask(img: DynamicImage, options: &SharpenOptions) -> DynamicImage {
+    if options.amount <= 0.0 || options.radius <= 0.0 {
+        return img;
+    }
+
+    let color = img.color();
+    let original = img.to_rgba32f();
+    let blurred = imageops::blur(&original, options.radius);
+    let threshold = options.threshold.unwrap_or(0) as f32 / 255.0;
+    // Float samples may hold HDR highlights above 1.0; only integer ones are bounded
+    let max = match color {
+        ColorType::Rgb32F | ColorType::Rgba32F => f32::INFINITY,
+        _ => 1.0,
+    };
+
+    let mut sharpened = Rgba32FImage::new(original.width(), original.height());
+    for ((out, src), blur) in sharpened.pixels_mut().zip(original.pixels()).zip(blurred.pixels()) {
+        for c in 0..3 {
+            let diff = src[c] - blur[c];
+            out[c] = if diff.abs() >= threshold {
+                (src[c] + options.amount * diff).clamp(0.0, max)
+            } else {
+                src[c]
+            };
+        }
+        // Alpha is never sharpened
+        out[3] = src[3];
+    }
+
+    to_color_type(DynamicImage::ImageRgba32F(sharpened), color)
+}
+
+// Why the resize step did (or did not) change the image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ResizeDecision {
+    #[serde(rename = "resized")]
+    Resized, // Scaled down (or cropped/padded to an exact size)
+    #[serde(rename = "enlarged")]
+    Enlarged, // Scaled up
+    #[serde(rename = "unchanged")]
+    Unchanged, // Already matches the request, or no size was requested
+    #[serde(rename = "enlargement_prevented")]
+    EnlargementPrevented, // Reaching the requested size needs enlarging, which is not allowed
+    #[serde(rename = "skipped_too_small")]
+    SkippedTooSmall, // Below skip_below_width / skip_below_height
+    #[serde(rename = "clamped_to_minimum")]
+    ClampedToMinimum, // Target was raised to min_output_width / min_output_height
+}
+
+// What the resize step will do, decided from the source dimensions alone so
+// that decoders can be told how small they may decode (shrink-on-load)
+#[derive(Debug, Clone)]
+pub enum ResizePlan {
+    Keep(ResizeDecision),
+    Exact {
+        width: u32,
+        height: u32,
+        decision: ResizeDecision,
+    },
+    Cover {
+        width: u32,
+        height: u32,
+        decision: ResizeDecision,
+    },
+    Contain {
+        width: u32,
+        height: u32,
+        background: Rgba<u8>,
+        decision: ResizeDecision,
+    },
+}
+
+pub struct ResizeOutcome {
+    pub image: DynamicImage,
+    pub focal_point: Option<FocalPoint>, // Crop center used by cover resizing
+    pub decision: ResizeDecision,
+}
+
+impl ResizePlan {
+    // Factor the source is resampled by, before any crop or padding. None when
+    // the image is kept as is.
+    fn scale(&self, source: (u32, u32)) -> Option<f64> {
+        let (width, height) = source;
+        let scale = match *self {
+            ResizePlan::Keep(_) => return None,
+            ResizePlan::Exact { width: w, height: h, .. } => {
+                f64::max(w as f64 / width as f64, h as f64 / height as f64)
+            }
+            ResizePlan::Cover { width: w, height: h, .. } => {
+                f64::max(w as f64 / width as f64, h as f64 / height as f64)
+            }
+            ResizePlan::Contain { width: w, height: h, .. } => {
+                f64::min(w as f64 / width as f64, h as f64 / height as f64)
+            }
+        };
+        Some(scale)
+    }
+
+    // Smallest decode size (aspect preserved) that still yields the same
+    // result. None when the full-resolution image is needed.
+    pub fn decode_hint(&self, source: (u32, u32)) -> Option<(u32, u32)> {
+        let (width, height) = source;
+        let scale = self.scale(source)?;
+        if scale >= 1.0 {
+            return None;
+        }
+        Some((
+            ((width as f64) * scale).ceil() as u32,
+            ((height as f64) * scale).ceil() as u32,
+        ))
+    }
+}
+
+// Raise an aspect-preserving target so both sides reach the configured
+// minimum. None when the target already does.
+fn raised_to_minimum(target: (u32, u32), settings: &ProcessingSettings) -> Option<(u32, u32)> {
+    let (target_w, target_h) = target;
+    let factor = f64::max(
+        settings.min_output_width.map_or(1.0, |min| min as f64 / target_w as f64),
+        settings.min_output_height.map_or(1.0, |min| min as f64 / target_h as f64),
+    );
+    if factor <= 1.0 {
+        return None;
+    }
+
+    let raised_w = ((target_w as f64) * factor).round() as u32;
+    let raised_h = ((target_h as f64) * factor).round() as u32;
+    Some((raised_w, raised_h))
+}
+
+// Decide the resize step described by `settings` for an image of `source`
+// dimensions. Missing mode parameters mean the image is kept as is.
+pub fn plan_resize(source: (u32, u32), settings: &ProcessingSettings) -> Result<ResizePlan, String> {
+    let allow_enlarge = settings.allow_enlarge.unwrap_or(false);
+    let (width, height) = source;
+    let (max_width, max_height) = (settings.max_width, settings.max_height);
+
+    let too_small = settings.skip_below_width.is_some_and(|min| width < min)
+        || settings.skip_below_height.is_some_and(|min| height < min);
+    if too_small {
+        return Ok(ResizePlan::Keep(ResizeDecision::SkippedTooSmall));
+    }
+
+    // Cover and contain produce exactly the requested box. When that would mean
+    // scaling the image up and enlarging is not allowed, the box is shrunk to
+    // the same aspect ratio at the source's own scale instead.
+    if let Some(ResizeMode::Cover) | Some(ResizeMode::Contain) = settings.resize_mode {
+        let (mut max_w, mut max_h) = match (max_width, max_height) {
+            (Some(max_w), Some(max_h)) if max_w > 0 && max_h > 0 => (max_w, max_h),
+            _ => return Err("Cover and contain resize require max_width and max_height".to_string()),
+        };
+        let cover = matches!(settings.resize_mode, Some(ResizeMode::Cover));
+        let (scale_w, scale_h) = (max_w as f64 / width as f64, max_h as f64 / height as f64);
+        let scale = if cover { scale_w.max(scale_h) } else { scale_w.min(scale_h) };
+
+        let decision = if scale <= 1.0 {
+            ResizeDecision::Resized
+        } else if allow_enlarge {
+            ResizeDecision::Enlarged
+        } else {
+            max_w = (((max_w as f64) / scale).round() as u32).max(1);
+            max_h = (((max_h as f64) / scale).round() as u32).max(1);
+            ResizeDecision::EnlargementPrevented
+        };
+
+        if cover {
+            return Ok(ResizePlan::Cover { width: max_w, height: max_h, decision });
+        }
+        let background = match &settings.background_color {
+            Some(color) => parse_hex_color(color)?,
+            None => DEFAULT_BACKGROUND,
+        };
+        return Ok(ResizePlan::Contain { width: max_w, height: max_h, background, decision });
+    }
+
+    // Every other mode boils down to a target size. `None` means no size was
+    // requested; otherwise (target, keeps_aspect, blocked).
+    let target = match settings.resize_mode {
+        Some(ResizeMode::Percentage) => {
+            // Resize by percentage
+            settings.resize_percentage.map(|percentage| {
+                let max_percentage = if allow_enlarge { 1000.0 } else { 100.0 };
+                let requested = percentage.clamp(1.0, 1000.0);
+                let percentage_decimal = requested.min(max_percentage) / 100.0;
+                let new_width = (((width as f32) * percentage_decimal).round() as u32).max(1);
+                let new_height = (((height as f32) * percentage_decimal).round() as u32).max(1);
+                let blocked = requested > max_percentage;
+                ((new_width, new_height), true, blocked)
+            })
+        }
+        Some(ResizeMode::Dimensions) | None => {
+            // Resize by dimensions
+            match (max_width, max_height) {
+                (Some(max_w), Some(max_h)) if max_w > 0 && max_h > 0 => {
+                    if settings.keep_aspect_ratio.unwrap_or(true) {
+                        // Fit within bounds, preserving the aspect ratio
+                        let scale = f64::min(max_w as f64 / width as f64, max_h as f64 / height as f64);
+                        let fitted = (
+                            (((width as f64) * scale).round() as u32).max(1),
+                            (((height as f64) * scale).round() as u32).max(1),
+                        );
+                        Some((fitted, true, false))
+                    } else {
+                        // Exact size (may distort image); historically only applied
+                        // when the image exceeds the bounds. Without enlarging,
+                        // each side only ever shrinks to its bound.
+                        let exceeds = width > max_w || height > max_h;
+                        let target = if allow_enlarge {
+                            (max_w, max_h)
+                        } else {
+                            (width.min(max_w), height.min(max_h))
+                        };
+                        Some((target, false, !exceeds && !allow_enlarge))
+                    }
+                }
+                _ => None,
+            }
+        }
+        Some(ResizeMode::LongEdge) | Some(ResizeMode::ShortEdge) => {
+            let length = settings.resize_length
+                .filter(|length| *length > 0)
+                .ok_or("Edge resize requires resize_length")?;
+            let side = if let Some(ResizeMode::LongEdge) = settings.resize_mode {
+                width.max(height)
+            } else {
+                width.min(height)
+            };
+            Some((scaled_to(width, height, side, length), true, false))
+        }
+        Some(ResizeMode::Width) => {
+            let max_w = max_width.filter(|w| *w > 0).ok_or("Width resize requires max_width")?;
+            Some((scaled_to(width, height, width, max_w), true, false))
+        }
+        Some(ResizeMode::Height) => {
+            let max_h = max_height.filter(|h| *h > 0).ok_or("Height resize requires max_height")?;
+            Some((scaled_to(width, height, height, max_h), true, false))
+        }
+        Some(ResizeMode::Cover) | Some(ResizeMode::Contain) => None,
+    };
+
+    let Some((mut target, keeps_aspect, mut blocked)) = target else {
+        return Ok(ResizePlan::Keep(ResizeDecision::Unchanged));
+    };
+
+    // Aspect-preserving targets larger than the source need enlarging
+    if keeps_aspect && (target.0 > width || target.1 > height) && !allow_enlarge {
+        target = (width, height);
+        blocked = true;
+    }
+
+    // The minimum may not push the target past the source unless enlarging is
+    // allowed; the image is then kept as is, which is not a clamp
+    let mut clamped = false;
+    if let Some(raised) = raised_to_minimum(target, settings).filter(|_| keeps_aspect) {
+        if !allow_enlarge && (raised.0 > width || raised.1 > height) {
+            target = (width, height);
+            blocked = true;
+        } else {
+            target = raised;
+            clamped = true;
+        }
+    }
+
+    if target == (width, height) || blocked {
+        let decision = if blocked {
+            ResizeDecision::EnlargementPrevented
+        } else if clamped {
+            ResizeDecision::ClampedToMinimum
+        } else {
+            ResizeDecision::Unchanged
+        };
+        return Ok(ResizePlan::Keep(decision));
+    }
+
+    let decision = if clamped {
+        ResizeDecision::ClampedToMinimum
+    } else if target.0 > width || target.1 > height {
+        ResizeDecision::Enlarged
+    } else {
+        ResizeDecision::Resized
+    };
+    Ok(ResizePlan::Exact { width: target.0, height: target.1, decision })
+}
+
+// Carry out a plan made for an image of `source` dimensions. `img` may be
+// smaller than `source` when the decoder shrank it on load.
+pub fn apply_plan(
+    img: DynamicImage,
+    plan: &ResizePlan,
+    source: (u32, u32),
+    settings: &ProcessingSettings,
+) -> ResizeOutcome {
+    let resampler = Resampler::for_plan(plan, source, settings);
+
+    let (image, focal_point, decision) = match *plan {
+        ResizePlan::Keep(decision) => (img, None, decision),
+        ResizePlan::Exact { width, height, decision } => {
+            let resized = if img.dimensions() == (width, height) {
+                resampler.sharpened(img)
+            } else {
+                resampler.resample(&img, width, height)
+            };
+            (resized, None, decision)
+        }
+        ResizePlan::Cover { width, height, decision } => {
+            let smart = settings.smart_crop.unwrap_or(false);
+            let (cropped, focal_point) = cover(&img, width, height, settings.focal_point, smart, resampler);
+            (cropped, focal_point, decision)
+        }
+        ResizePlan::Contain { width, height, background, decision } => {
+            (contain(&img, width, height, background, resampler), None, decision)
+        }
+    };
+
+    ResizeOutcome {
+        image,
+        focal_point,
+        decision,
+    }
+}
+
+// Plan and apply the resize step for an already decoded image
+pub fn resize_image(img: DynamicImage, settings: &ProcessingSettings) -> Result<ResizeOutcome, String> {
+    let source = img.dimensions();
+    let plan = plan_resize(source, settings)?;
+    Ok(apply_plan(img, &plan, source, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn settings(mode: ResizeMode, max_width: u32, max_height: u32) -> ProcessingSettings {
+        ProcessingSettings {
+            resize_mode: Some(mode),
+            max_width: Some(max_width),
+            max_height: Some(max_height),
+            ..Default::default()
+        }
+    }
+
+    fn box_of(plan: &ResizePlan) -> (u32, u32, ResizeDecision) {
+        match *plan {
+            ResizePlan::Keep(decision) => (0, 0, decision),
+            ResizePlan::Exact { width, height, decision }
+            | ResizePlan::Cover { width, height, decision }
+            | ResizePlan::Contain { width, height, decision, .. } => (width, height, decision),
+        }
+    }
+
+    fn plan(source: (u32, u32), settings: &ProcessingSettings) -> (u32, u32, ResizeDecision) {
+        box_of(&plan_resize(source, settings).unwrap())
+    }
+
+    #[test]
+    fn cover_downscale_is_resized() {
+        let plan = plan((2000, 1000), &settings(ResizeMode::Cover, 800, 800));
+        assert_eq!(plan, (800, 800, ResizeDecision::Resized));
+    }
+
+    #[test]
+    fn cover_upscaling_one_side_is_prevented_by_default() {
+        // 800 / 500 = 1.6× even though the width shrinks
+        let plan = plan((1000, 500), &settings(ResizeMode::Cover, 800, 800));
+        assert_eq!(plan, (500, 500, ResizeDecision::EnlargementPrevented));
+    }
+
+    #[test]
+    fn cover_upscaling_is_reported_when_allowed() {
+        let mut settings = settings(ResizeMode::Cover, 800, 800);
+        settings.allow_enlarge = Some(true);
+        assert_eq!(plan((1000, 500), &settings), (800, 800, ResizeDecision::Enlarged));
+    }
+
+    #[test]
+    fn contain_keeps_small_images_at_source_scale() {
+        let plan = plan((400, 200), &settings(ResizeMode::Contain, 800, 800));
+        assert_eq!(plan, (400, 400, ResizeDecision::EnlargementPrevented));
+    }
+
+    #[test]
+    fn contain_downscale_is_resized() {
+        let plan = plan((2000, 1000), &settings(ResizeMode::Contain, 800, 800));
+        assert_eq!(plan, (800, 800, ResizeDecision::Resized));
+    }
+
+    #[test]
+    fn cover_requires_both_dimensions() {
+        let mut settings = settings(ResizeMode::Cover, 800, 800);
+        settings.max_height = None;
+        assert!(plan_resize((1000, 500), &settings).is_err());
+    }
+
+    #[test]
+    fn dimensions_fit_inside_the_box() {
+        let plan = plan((2000, 1000), &settings(ResizeMode::Dimensions, 800, 800));
+        assert_eq!(plan, (800, 400, ResizeDecision::Resized));
+    }
+
+    #[test]
+    fn dimensions_never_enlarge_by_default() {
+        let plan = plan((400, 200), &settings(ResizeMode::Dimensions, 800, 800));
+        assert_eq!(plan, (0, 0, ResizeDecision::EnlargementPrevented));
+    }
+
+    #[test]
+    fn exact_dimensions_never_stretch_the_short_side() {
+        let mut settings = settings(ResizeMode::Dimensions, 2000, 1000);
+        settings.keep_aspect_ratio = Some(false);
+        assert_eq!(plan((3000, 500), &settings), (2000, 500, ResizeDecision::Resized));
+        settings.allow_enlarge = Some(true);
+        assert_eq!(plan((3000, 500), &settings), (2000, 1000, ResizeDecision::Enlarged));
+    }
+
+    #[test]
+    fn percentage_above_100_is_capped() {
+        let mut settings = settings(ResizeMode::Percentage, 0, 0);
+        settings.resize_percentage = Some(200.0);
+        assert_eq!(plan((400, 200), &settings), (0, 0, ResizeDecision::EnlargementPrevented));
+        settings.resize_percentage = Some(50.0);
+        assert_eq!(plan((400, 200), &settings), (200, 100, ResizeDecision::Resized));
+    }
+
+    #[test]
+    fn small_images_are_skipped() {
+        let mut settings = settings(ResizeMode::Dimensions, 100, 100);
+        settings.skip_below_width = Some(500);
+        assert_eq!(plan((400, 200), &settings), (0, 0, ResizeDecision::SkippedTooSmall));
+    }
+
+    #[test]
+    fn target_is_raised_to_the_minimum() {
+        let mut settings = settings(ResizeMode::Dimensions, 100, 100);
+        settings.min_output_width = Some(200);
+        assert_eq!(plan((1000, 500), &settings), (200, 100, ResizeDecision::ClampedToMinimum));
+    }
+
+    #[test]
+    fn minimum_above_the_source_is_prevented() {
+        let mut settings = settings(ResizeMode::Dimensions, 100, 100);
+        settings.min_output_width = Some(2000);
+        assert_eq!(plan((1000, 500), &settings), (0, 0, ResizeDecision::EnlargementPrevented));
+        settings.allow_enlarge = Some(true);
+        assert_eq!(plan((1000, 500), &settings), (2000, 1000, ResizeDecision::ClampedToMinimum));
+    }
+
+    #[test]
+    fn minimum_does_not_hide_a_prevented_enlargement() {
+        // The box already needs enlarging; the minimum would need even more
+        let mut settings = settings(ResizeMode::Dimensions, 800, 800);
+        settings.min_output_width = Some(600);
+        assert_eq!(plan((400, 200), &settings), (0, 0, ResizeDecision::EnlargementPrevented));
+    }
+
+    #[test]
+    fn long_edge_scales_the_longer_side() {
+        let mut settings = settings(ResizeMode::LongEdge, 0, 0);
+        settings.resize_length = Some(500);
+        assert_eq!(plan((1000, 2000), &settings), (250, 500, ResizeDecision::Resized));
+    }
+
+    // 32×8 with a vertical edge between columns 15 and 16
+    fn step_edge(dark: f32, light: f32) -> Rgba32FImage {
+        Rgba32FImage::from_fn(32, 8, |x, _| {
+            let v = if x < 16 { dark } else { light };
+            Rgba([v, v, v, 1.0])
+        })
+    }
+
+    fn sharpen(amount: f32) -> SharpenOptions {
+        SharpenOptions { amount, radius: 1.5, threshold: None }
+    }
+
+    #[test]
+    fn sharpen_with_zero_amount_is_a_no_op() {
+        let img = DynamicImage::ImageRgba32F(step_edge(0.25, 0.75));
+        let sharpened = unsharp_mask(img.clone(), &sharpen(0.0));
+        assert_eq!(sharpened, img);
+    }
+
+    #[test]
+    fn sharpen_increases_edge_contrast() {
+        let img = DynamicImage::ImageRgba32F(step_edge(0.25, 0.75)).to_luma8();
+        let sharpened = unsharp_mask(DynamicImage::ImageLuma8(img.clone()), &sharpen(1.0)).to_luma8();
+        assert_eq!(sharpened.dimensions(), img.dimensions());
+        assert!(sharpened.get_pixel(15, 4)[0] < img.get_pixel(15, 4)[0]);
+        assert!(sharpened.get_pixel(16, 4)[0] > img.get_pixel(16, 4)[0]);
+        // Flat areas away from the edge are untouched
+        assert_eq!(sharpened.get_pixel(2, 4), img.get_pixel(2, 4));
+    }
+
+    #[test]
+    fn sharpen_keeps_float_highlights_above_one() {
+        let img = DynamicImage::ImageRgba32F(step_edge(0.5, 4.0));
+        let sharpened = unsharp_mask(img, &sharpen(1.0)).to_rgba32f();
+        assert_eq!(sharpened.get_pixel(30, 4)[0], 4.0);
+        assert!(sharpened.get_pixel(16, 4)[0] > 4.0);
+    }
+
+    #[test]
+    fn contain_sharpens_before_padding() {
+        // 1200×100 into 1000×1000 is a downscale even though the canvas is larger
+        let stripes = DynamicImage::ImageRgb8(RgbImage::from_fn(1200, 100, |x, _| {
+            let v = if (x / 20) % 2 == 0 { 40 } else { 220 };
+            image::Rgb([v, v, v])
+        }));
+        let mut settings = settings(ResizeMode::Contain, 1000, 1000);
+        settings.background_color = Some("#ff0000".to_string());
+        let plain = resize_image(stripes.clone(), &settings).unwrap().image.to_rgba8();
+        settings.sharpen = Some(sharpen(1.0));
+        let sharpened = resize_image(stripes, &settings).unwrap().image.to_rgba8();
+
+        assert_ne!(sharpened, plain);
+        // Scaled to 1000×83 and centered at y = 458; every other row is padding
+        let background = Rgba([255, 0, 0, 255]);
+        for (_, y, pixel) in sharpened.enumerate_pixels() {
+            if !(458..541).contains(&y) {
+                assert_eq!(*pixel, background, "row {}", y);
+            }
+        }
+    }
+
+    // A horizontal gradient, downscaled 4× with both backends
+    fn check_filter(filter: ResizeFilter) {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 48, |x, _| {
+            let v = (x * 4) as u8;
+            image::Rgb([v, v, v])
+        }));
+        for backend in [ResizeBackend::Standard, ResizeBackend::Fast] {
+            let resized = Resampler { filter, backend, sharpen: None }.resize_exact(&img, 16, 12);
+            assert_eq!(resized.dimensions(), (16, 12));
+            assert_eq!(resized.color(), ColorType::Rgb8);
+            // The gradient survives: left is dark, right is light, rows agree
+            let rgb = resized.to_rgb8();
+            assert!(rgb.get_pixel(0, 6)[0] < 32, "{:?} {:?}", filter, backend);
+            assert!(rgb.get_pixel(15, 6)[0] > 220, "{:?} {:?}", filter, backend);
+            assert_eq!(rgb.get_pixel(8, 0), rgb.get_pixel(8, 11));
+        }
+    }
+
+    #[test]
+    fn nearest_filter_resizes() {
+        check_filter(ResizeFilter::Nearest);
+    }
+
+    #[test]
+    fn triangle_filter_resizes() {
+        check_filter(ResizeFilter::Triangle);
+    }
+
+    #[test]
+    fn catmull_rom_filter_resizes() {
+        check_filter(ResizeFilter::CatmullRom);
+    }
+
+    #[test]
+    fn gaussian_filter_resizes() {
+        check_filter(ResizeFilter::Gaussian);
+    }
+
+    #[test]
+    fn lanczos3_filter_resizes() {
+        check_filter(ResizeFilter::Lanczos3);
+    }
+}
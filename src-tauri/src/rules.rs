@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::decode::{self, SourceHeader};
+use crate::{formats, ProcessingSettings};
+
+// Partial settings object; any key present replaces the base setting, and
+// nested objects such as `jpeg` are merged key by key. Kept as raw JSON so
+// overrides automatically cover every field of ProcessingSettings.
+pub type SettingsPatch = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleMatch {
+    pub glob: Option<String>, // Matched against the file name, or the full path if it contains a separator
+    pub formats: Option<Vec<String>>, // Source format by any of its extensions, e.g. "jpeg", "heic", "svg"
+    pub min_width: Option<u32>,
+    pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+    pub has_alpha: Option<bool>, // Source color type has an alpha channel
+}
+
+// Settings applied to every file that satisfies all conditions in `match`.
+// Rules are applied in order, so later rules win over earlier ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsRule {
+    #[serde(rename = "match")]
+    pub matcher: RuleMatch,
+    pub settings: SettingsPatch,
+}
+
+impl RuleMatch {
+    fn needs_header(&self) -> bool {
+        self.min_width.is_some()
+            || self.max_width.is_some()
+            || self.min_height.is_some()
+            || self.max_height.is_some()
+            || self.has_alpha.is_some()
+    }
+
+    fn matches(&self, path: &Path, header: Option<&SourceHeader>) -> Result<bool, String> {
+        if let Some(pattern) = &self.glob {
+            let pattern = glob::Pattern::new(pattern)
+                .map_err(|e| format!("Invalid rule glob \"{}\": {}", pattern, e))?;
+            let matched = if pattern.as_str().contains(['/', '\\']) {
+                pattern.matches_path(path)
+            } else {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| pattern.matches(name))
+            };
+            if !matched {
+                return Ok(false);
+            }
+        }
+
+        if let Some(names) = &self.formats {
+            if !names.iter().any(|name| formats::is_format(path, name)) {
+                return Ok(false);
+            }
+        }
+
+        if !self.needs_header() {
+            return Ok(true);
+        }
+        // Dimension and alpha conditions never match unreadable files
+        let Some(header) = header else {
+            return Ok(false);
+        };
+
+        let within = |value: u32, min: Option<u32>, max: Option<u32>| {
+            min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+        };
+        Ok(within(header.width, self.min_width, self.max_width)
+            && within(header.height, self.min_height, self.max_height)
+            && self.has_alpha.is_none_or(|alpha| alpha == header.has_alpha))
+    }
+}
+
+// Overwrite `base` with `patch`, recursing where both sides are objects so a
+// patch of `{"jpeg": {"progressive": true}}` keeps the other JPEG options
+fn merge(base: &mut serde_json::Value, patch: &serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, patch_value) in patch {
+                match base.get_mut(key) {
+                    Some(base_value) => merge(base_value, patch_value),
+                    None => {
+                        base.insert(key.clone(), patch_value.clone());
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
+
+fn apply_patch(settings: &ProcessingSettings, patch: &SettingsPatch) -> Result<ProcessingSettings, String> {
+    let mut value = serde_json::to_value(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    // Every field serializes, even when unset, so the base object lists all
+    // known keys. Anything else is a typo that serde would silently drop.
+    if let Some(object) = value.as_object() {
+        if let Some(key) = patch.keys().find(|key| !object.contains_key(*key)) {
+            return Err(format!("Unknown setting \"{}\" in settings override", key));
+        }
+    }
+
+    merge(&mut value, &serde_json::Value::Object(patch.clone()));
+    serde_json::from_value(value)
+        .map_err(|e| format!("Invalid settings override: {}", e))
+}
+
+// Resolve the effective settings for one file: matching rules in order, then
+// the per-path override. Returns the settings (with rules cleared) and the
+// indices of the rules that matched.
+pub fn resolve_settings(
+    base: &ProcessingSettings,
+    path: &Path,
+    path_override: Option<&SettingsPatch>,
+) -> Result<(ProcessingSettings, Vec<usize>), String> {
+    let mut resolved = base.clone();
+    let rules = resolved.rules.take().unwrap_or_default();
+    let mut applied = Vec::new();
+
+    // SVG dimensions are those of the raster the base settings would produce
+    let header = if rules.iter().any(|rule| rule.matcher.needs_header()) {
+        decode::read_header(path, &base.svg.clone().unwrap_or_default()).ok()
+    } else {
+        None
+    };
+
+    for (index, rule) in rules.iter().enumerate() {
+        if rule.matcher.matches(path, header.as_ref())? {
+            resolved = apply_patch(&resolved, &rule.settings)?;
+            applied.push(index);
+        }
+    }
+
+    if let Some(patch) = path_override {
+        resolved = apply_patch(&resolved, patch)?;
+    }
+
+    // Overrides cannot smuggle in nested rules
+    resolved.rules = None;
+    Ok((resolved, applied))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patch(value: serde_json::Value) -> SettingsPatch {
+        value.as_object().unwrap().clone()
+    }
+
+    fn rule(matcher: serde_json::Value, settings: serde_json::Value) -> SettingsRule {
+        SettingsRule {
+            matcher: serde_json::from_value(matcher).unwrap(),
+            settings: patch(settings),
+        }
+    }
+
+    fn header(width: u32, height: u32, has_alpha: bool) -> SourceHeader {
+        SourceHeader { width, height, bytes_per_pixel: 4, has_alpha }
+    }
+
+    fn matcher(value: serde_json::Value) -> RuleMatch {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn glob_without_separator_matches_the_file_name() {
+        let rule = matcher(json!({ "glob": "*.png" }));
+        assert!(rule.matches(Path::new("/photos/2024/icon.png"), None).unwrap());
+        assert!(!rule.matches(Path::new("/photos/2024/icon.jpg"), None).unwrap());
+    }
+
+    #[test]
+    fn glob_with_separator_matches_the_full_path() {
+        let rule = matcher(json!({ "glob": "/photos/*/raw/*.png" }));
+        assert!(rule.matches(Path::new("/photos/2024/raw/a.png"), None).unwrap());
+        assert!(!rule.matches(Path::new("/photos/2024/edited/a.png"), None).unwrap());
+        // A file name alone never matches a path pattern
+        assert!(!rule.matches(Path::new("a.png"), None).unwrap());
+    }
+
+    #[test]
+    fn invalid_glob_is_an_error() {
+        let rule = matcher(json!({ "glob": "[" }));
+        assert!(rule.matches(Path::new("a.png"), None).is_err());
+    }
+
+    #[test]
+    fn formats_match_any_extension_including_input_only_formats() {
+        let rule = matcher(json!({ "formats": ["jpeg", "heif", "svg"] }));
+        assert!(rule.matches(Path::new("a.jpg"), None).unwrap());
+        assert!(rule.matches(Path::new("IMG_0001.HEIC"), None).unwrap());
+        assert!(rule.matches(Path::new("logo.svg"), None).unwrap());
+        assert!(!rule.matches(Path::new("a.png"), None).unwrap());
+    }
+
+    #[test]
+    fn dimension_bounds_are_inclusive() {
+        let rule = matcher(json!({ "min_width": 100, "max_width": 200, "max_height": 50 }));
+        let path = Path::new("a.png");
+        assert!(rule.matches(path, Some(&header(100, 50, false))).unwrap());
+        assert!(rule.matches(path, Some(&header(200, 10, false))).unwrap());
+        assert!(!rule.matches(path, Some(&header(99, 50, false))).unwrap());
+        assert!(!rule.matches(path, Some(&header(201, 50, false))).unwrap());
+        assert!(!rule.matches(path, Some(&header(150, 51, false))).unwrap());
+    }
+
+    #[test]
+    fn header_conditions_never_match_unreadable_files() {
+        let rule = matcher(json!({ "has_alpha": true }));
+        assert!(rule.matches(Path::new("a.png"), Some(&header(1, 1, true))).unwrap());
+        assert!(!rule.matches(Path::new("a.png"), Some(&header(1, 1, false))).unwrap());
+        assert!(!rule.matches(Path::new("a.png"), None).unwrap());
+    }
+
+    #[test]
+    fn rules_apply_in_order_then_the_override() {
+        let base = ProcessingSettings {
+            quality: Some(50.0),
+            rules: Some(vec![
+                rule(json!({ "glob": "*.png" }), json!({ "quality": 60.0, "max_width": 800 })),
+                rule(json!({ "glob": "*.jpg" }), json!({ "quality": 70.0 })),
+                rule(json!({ "glob": "icon*" }), json!({ "quality": 80.0 })),
+            ]),
+            ..ProcessingSettings::default()
+        };
+
+        let (resolved, applied) = resolve_settings(&base, Path::new("icon.png"), None).unwrap();
+        assert_eq!(applied, vec![0, 2]);
+        assert_eq!(resolved.quality, Some(80.0));
+        assert_eq!(resolved.max_width, Some(800));
+        assert!(resolved.rules.is_none());
+
+        let override_patch = patch(json!({ "quality": 90.0, "rules": [] }));
+        let (resolved, applied) =
+            resolve_settings(&base, Path::new("icon.png"), Some(&override_patch)).unwrap();
+        assert_eq!(applied, vec![0, 2]);
+        assert_eq!(resolved.quality, Some(90.0));
+        assert_eq!(resolved.max_width, Some(800));
+        assert!(resolved.rules.is_none());
+    }
+
+    #[test]
+    fn header_conditions_read_the_source_file() {
+        let path = std::env::temp_dir().join(format!("optisnap-rules-{}.png", std::process::id()));
+        image::RgbaImage::new(120, 40).save(&path).unwrap();
+        let base = ProcessingSettings {
+            rules: Some(vec![
+                rule(json!({ "min_width": 100, "has_alpha": true }), json!({ "quality": 60.0 })),
+                rule(json!({ "min_height": 100 }), json!({ "quality": 70.0 })),
+            ]),
+            ..ProcessingSettings::default()
+        };
+        let resolved = resolve_settings(&base, &path, None);
+        std::fs::remove_file(&path).ok();
+
+        let (resolved, applied) = resolved.unwrap();
+        assert_eq!(applied, vec![0]);
+        assert_eq!(resolved.quality, Some(60.0));
+    }
+
+    #[test]
+    fn unmatched_files_keep_the_base_settings() {
+        let base = ProcessingSettings {
+            quality: Some(50.0),
+            rules: Some(vec![rule(json!({ "formats": ["png"] }), json!({ "quality": 60.0 }))]),
+            ..ProcessingSettings::default()
+        };
+        let (resolved, applied) = resolve_settings(&base, Path::new("a.webp"), None).unwrap();
+        assert!(applied.is_empty());
+        assert_eq!(resolved.quality, Some(50.0));
+    }
+
+    #[test]
+    fn invalid_patch_is_an_error() {
+        let base = ProcessingSettings::default();
+        let bad = patch(json!({ "quality": "high" }));
+        assert!(resolve_settings(&base, Path::new("a.png"), Some(&bad)).is_err());
+    }
+
+    #[test]
+    fn unknown_patch_keys_are_an_error() {
+        let base = ProcessingSettings::default();
+        let typo = patch(json!({ "qualty": 70.0 }));
+        let error = resolve_settings(&base, Path::new("a.png"), Some(&typo)).unwrap_err();
+        assert!(error.contains("qualty"), "{}", error);
+    }
+
+    #[test]
+    fn nested_patches_keep_untouched_options() {
+        let base = ProcessingSettings {
+            jpeg: Some(serde_json::from_value(json!({ "progressive": false, "restart_interval": 8 })).unwrap()),
+            ..ProcessingSettings::default()
+        };
+        let progressive = patch(json!({ "jpeg": { "progressive": true } }));
+        let (resolved, _) = resolve_settings(&base, Path::new("a.jpg"), Some(&progressive)).unwrap();
+
+        let jpeg = resolved.jpeg.unwrap();
+        assert_eq!(jpeg.progressive, Some(true));
+        assert_eq!(jpeg.restart_interval, Some(8));
+    }
+}
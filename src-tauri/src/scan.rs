@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{Emitter, State};
+
+use crate::{decode, formats};
+
+// Number of discovered files collected before a `scan-progress` event is emitted
+const SCAN_BATCH_SIZE: usize = 256;
+
+// Cancellation flag for folder scans, separate from the batch flag so that
+// cancelling a scan never aborts a running batch (and vice versa)
+pub struct ScanCancellationFlag(pub Arc<AtomicBool>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedFile {
+    pub path: String,
+    pub size: u64,
+    pub width: Option<u32>, // None when the header could not be read
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanError {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProgress {
+    pub files: Vec<ScannedFile>, // Files discovered since the previous event
+    pub found_count: usize,
+    pub error_count: usize,
+    pub scanned_dirs: usize,
+    pub current_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub files: Vec<ScannedFile>,
+    pub errors: Vec<ScanError>,
+    pub cancelled: bool,
+}
+
+fn scanned_file(path: &Path, size: u64) -> Option<ScannedFile> {
+    let path_str = path.to_str()?.to_string();
+    // Only the header is read here, so this stays cheap even for huge images
+    let dimensions = decode::image_dimensions(path).ok();
+
+    Some(ScannedFile {
+        path: path_str,
+        size,
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+    })
+}
+
+fn scan_directory_tree(
+    root: &Path,
+    cancel_flag: &AtomicBool,
+    mut on_batch: impl FnMut(ScanProgress),
+) -> ScanResult {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    let mut pending = Vec::new();
+    let mut scanned_dirs = 0;
+    let mut cancelled = false;
+
+    // Iterative walk so deeply nested trees cannot overflow the stack
+    let mut dirs: Vec<PathBuf> = vec![root.to_path_buf()];
+    // Canonical paths of directories already walked. Symlinks and junctions
+    // are followed, so a link back to an ancestor would otherwise loop forever.
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+
+    while let Some(dir) = dirs.pop() {
+        if cancel_flag.load(Ordering::Relaxed) {
+            cancelled = true;
+            break;
+        }
+
+        let canonical = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !visited.insert(canonical) {
+            continue;
+        }
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(ScanError {
+                    path: dir.to_string_lossy().to_string(),
+                    error: format!("Failed to read directory: {}", e),
+                });
+                continue;
+            }
+        };
+        scanned_dirs += 1;
+
+        for entry in entries {
+            if cancel_flag.load(Ordering::Relaxed) {
+                cancelled = true;
+                break;
+            }
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    errors.push(ScanError {
+                        path: dir.to_string_lossy().to_string(),
+                        error: format!("Failed to read entry: {}", e),
+                    });
+                    continue;
+                }
+            };
+            let path = entry.path();
+
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    errors.push(ScanError {
+                        path: path.to_string_lossy().to_string(),
+                        error: format!("Failed to read metadata: {}", e),
+                    });
+                    continue;
+                }
+            };
+
+            if metadata.is_dir() {
+                dirs.push(path);
+            } else if metadata.is_file() && formats::is_supported_input(&path) {
+                if let Some(file) = scanned_file(&path, metadata.len()) {
+                    pending.push(file);
+                }
+            }
+
+            if pending.len() >= SCAN_BATCH_SIZE {
+                files.extend(pending.iter().cloned());
+                on_batch(ScanProgress {
+                    files: std::mem::take(&mut pending),
+                    found_count: files.len(),
+                    error_count: errors.len(),
+                    scanned_dirs,
+                    current_dir: Some(dir.to_string_lossy().to_string()),
+                });
+            }
+        }
+
+        if cancelled {
+            break;
+        }
+    }
+
+    // Flush whatever is left, even when cancelled, so the UI matches the result
+    files.extend(pending.iter().cloned());
+    on_batch(ScanProgress {
+        files: pending,
+        found_count: files.len(),
+        error_count: errors.len(),
+        scanned_dirs,
+        current_dir: None,
+    });
+
+    ScanResult {
+        files,
+        errors,
+        cancelled,
+    }
+}
+
+#[tauri::command]
+pub async fn scan_folder_for_images(
+    folder_path: String,
+    app: tauri::AppHandle,
+    scan_flag: State<'_, ScanCancellationFlag>,
+) -> Result<ScanResult, String> {
+    let cancel_flag = scan_flag.0.clone();
+    cancel_flag.store(false, Ordering::Relaxed);
+
+    let folder = PathBuf::from(&folder_path);
+    if !folder.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        scan_directory_tree(&folder, &cancel_flag, |progress| {
+            let _ = app.emit("scan-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to execute folder scan: {}", e))
+}
+
+#[tauri::command]
+pub fn cancel_scan(scan_flag: State<ScanCancellationFlag>) -> Result<String, String> {
+    scan_flag.0.store(true, Ordering::Relaxed);
+    Ok("Folder scan cancellation requested".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Scratch directory removed when the test ends
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("optisnap-scan-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn image(&self, relative: &str) {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            image::RgbImage::new(3, 2).save(&path).unwrap();
+        }
+
+        fn touch(&self, relative: &str) {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn scan(root: &Path) -> ScanResult {
+        scan_directory_tree(root, &AtomicBool::new(false), |_| {})
+    }
+
+    fn names(result: &ScanResult) -> Vec<String> {
+        let mut names: Vec<String> = result.files.iter()
+            .map(|file| Path::new(&file.path).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn nested_directories_are_walked() {
+        let dir = TempDir::new("nested");
+        dir.image("a.png");
+        dir.image("one/b.jpg");
+        dir.image("one/two/three/c.bmp");
+
+        let result = scan(&dir.0);
+        assert_eq!(names(&result), ["a.png", "b.jpg", "c.bmp"]);
+        assert!(result.files.iter().all(|file| file.width == Some(3) && file.height == Some(2)));
+        assert!(result.errors.is_empty());
+        assert!(!result.cancelled);
+    }
+
+    #[test]
+    fn unsupported_extensions_are_skipped() {
+        let dir = TempDir::new("unsupported");
+        dir.image("keep.png");
+        dir.touch("notes.txt");
+        dir.touch("output-only.avif");
+        dir.touch("no-extension");
+
+        assert_eq!(names(&scan(&dir.0)), ["keep.png"]);
+    }
+
+    #[test]
+    fn unreadable_images_are_listed_without_dimensions() {
+        let dir = TempDir::new("unreadable");
+        dir.touch("empty.png");
+
+        let result = scan(&dir.0);
+        assert_eq!(names(&result), ["empty.png"]);
+        assert_eq!(result.files[0].width, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_are_walked_once() {
+        let dir = TempDir::new("cycle");
+        dir.image("a.png");
+        dir.image("sub/b.png");
+        std::os::unix::fs::symlink(&dir.0, dir.0.join("sub/back-to-root")).unwrap();
+
+        let result = scan(&dir.0);
+        assert_eq!(names(&result), ["a.png", "b.png"]);
+    }
+
+    #[test]
+    fn cancelled_before_starting() {
+        let dir = TempDir::new("cancel-early");
+        dir.image("a.png");
+
+        let result = scan_directory_tree(&dir.0, &AtomicBool::new(true), |_| {});
+        assert!(result.cancelled);
+        assert!(result.files.is_empty());
+    }
+
+    #[test]
+    fn cancelled_during_the_scan() {
+        let dir = TempDir::new("cancel-during");
+        for i in 0..SCAN_BATCH_SIZE + 50 {
+            dir.touch(&format!("{}.png", i));
+        }
+
+        let cancel = AtomicBool::new(false);
+        let mut batches = Vec::new();
+        let result = scan_directory_tree(&dir.0, &cancel, |progress| {
+            cancel.store(true, Ordering::Relaxed);
+            batches.push(progress.files.len());
+        });
+
+        assert!(result.cancelled);
+        assert_eq!(result.files.len(), SCAN_BATCH_SIZE);
+        // The first full batch, then the final flush
+        assert_eq!(batches, [SCAN_BATCH_SIZE, 0]);
+    }
+}
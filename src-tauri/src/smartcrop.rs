@@ -0,0 +1,226 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+// Saliency is computed on a thumbnail; crop quality barely improves beyond this
+const ANALYSIS_SIZE: u32 = 256;
+// Side length (in thumbnail pixels) of the cells used for local entropy
+const ENTROPY_CELL: u32 = 16;
+
+const EDGE_WEIGHT: f32 = 1.0;
+const SKIN_WEIGHT: f32 = 1.8;
+const SATURATION_WEIGHT: f32 = 0.3;
+const ENTROPY_WEIGHT: f32 = 0.6;
+// Penalize windows far from the center slightly, so flat images stay centered
+const CENTER_BIAS: f32 = 0.15;
+
+// Point of interest in normalized coordinates (0.0 - 1.0, origin top-left)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FocalPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl FocalPoint {
+    pub fn clamped(self) -> FocalPoint {
+        FocalPoint {
+            x: self.x.clamp(0.0, 1.0),
+            y: self.y.clamp(0.0, 1.0),
+        }
+    }
+}
+
+// Skin tones cluster tightly in the CbCr plane regardless of brightness
+fn is_skin(r: f32, g: f32, b: f32) -> bool {
+    let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+    let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+    (77.0..=127.0).contains(&cb) && (133.0..=173.0).contains(&cr)
+}
+
+fn saliency_map(thumb: &DynamicImage) -> (Vec<f32>, u32, u32) {
+    let rgb = thumb.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let luma: Vec<f32> = rgb.pixels()
+        .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+        .collect();
+    let at = |x: u32, y: u32| luma[(y * width + x) as usize];
+
+    // Local entropy of the luma histogram per cell, normalized to 0..1
+    let cells_x = width.div_ceil(ENTROPY_CELL);
+    let cells_y = height.div_ceil(ENTROPY_CELL);
+    let mut entropy = vec![0.0f32; (cells_x * cells_y) as usize];
+    for cy in 0..cells_y {
+        for cx in 0..cells_x {
+            let mut histogram = [0u32; 32];
+            let mut count = 0u32;
+            for y in (cy * ENTROPY_CELL)..((cy + 1) * ENTROPY_CELL).min(height) {
+                for x in (cx * ENTROPY_CELL)..((cx + 1) * ENTROPY_CELL).min(width) {
+                    histogram[(at(x, y) as usize / 8).min(31)] += 1;
+                    count += 1;
+                }
+            }
+            let value: f32 = histogram.iter()
+                .filter(|&&n| n > 0)
+                .map(|&n| {
+                    let p = n as f32 / count as f32;
+                    -p * p.log2()
+                })
+                .sum();
+            entropy[(cy * cells_x + cx) as usize] = value / 5.0; // log2(32)
+        }
+    }
+
+    let mut scores = vec![0.0f32; (width * height) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let dx = at((x + 1).min(width - 1), y) - at(x.saturating_sub(1), y);
+            let dy = at(x, (y + 1).min(height - 1)) - at(x, y.saturating_sub(1));
+            let edge = ((dx.abs() + dy.abs()) / 510.0).min(1.0);
+
+            let p = rgb.get_pixel(x, y);
+            let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+            let max = r.max(g).max(b);
+            let min = r.min(g).min(b);
+            let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+            let skin = if is_skin(r, g, b) { 1.0 } else { 0.0 };
+
+            let cell = (y / ENTROPY_CELL) * cells_x + (x / ENTROPY_CELL);
+            scores[(y * width + x) as usize] = EDGE_WEIGHT * edge
+                + SKIN_WEIGHT * skin
+                + SATURATION_WEIGHT * saturation
+                + ENTROPY_WEIGHT * entropy[cell as usize];
+        }
+    }
+
+    (scores, width, height)
+}
+
+// Choose where a `crop_w` x `crop_h` window should sit inside `img` so that it
+// covers the most salient content. Returns the window center, normalized.
+pub fn find_focal_point(img: &DynamicImage, crop_w: u32, crop_h: u32) -> FocalPoint {
+    let (width, height) = img.dimensions();
+    let center = FocalPoint { x: 0.5, y: 0.5 };
+    if crop_w >= width && crop_h >= height {
+        return center;
+    }
+
+    let thumb = img.resize(ANALYSIS_SIZE, ANALYSIS_SIZE, FilterType::Triangle);
+    let (scores, thumb_w, thumb_h) = saliency_map(&thumb);
+    let scale_x = thumb_w as f32 / width as f32;
+    let scale_y = thumb_h as f32 / height as f32;
+    let window_w = ((crop_w as f32 * scale_x).round() as u32).clamp(1, thumb_w);
+    let window_h = ((crop_h as f32 * scale_y).round() as u32).clamp(1, thumb_h);
+
+    // Summed-area table so every window sum is O(1)
+    let stride = (thumb_w + 1) as usize;
+    let mut integral = vec![0.0f64; stride * (thumb_h + 1) as usize];
+    for y in 0..thumb_h as usize {
+        let mut row = 0.0f64;
+        for x in 0..thumb_w as usize {
+            row += scores[y * thumb_w as usize + x] as f64;
+            integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row;
+        }
+    }
+    let window_sum = |x: usize, y: usize| {
+        let (x2, y2) = (x + window_w as usize, y + window_h as usize);
+        integral[y2 * stride + x2] - integral[y * stride + x2] - integral[y2 * stride + x]
+            + integral[y * stride + x]
+    };
+
+    let total = integral[thumb_h as usize * stride + thumb_w as usize].max(f64::EPSILON);
+    let max_x = thumb_w - window_w;
+    let max_y = thumb_h - window_h;
+    // Start from the center, so images without any salient content (where
+    // every window scores zero) are not cropped from the top-left corner
+    let (center_x, center_y) = (max_x / 2, max_y / 2);
+    let mut best = (window_sum(center_x as usize, center_y as usize) / total, center_x, center_y);
+
+    for y in 0..=max_y {
+        for x in 0..=max_x {
+            let off_x = if max_x > 0 { (x as f32 / max_x as f32) - 0.5 } else { 0.0 };
+            let off_y = if max_y > 0 { (y as f32 / max_y as f32) - 0.5 } else { 0.0 };
+            let bias = 1.0 - CENTER_BIAS * (off_x.abs() + off_y.abs());
+
+            let score = window_sum(x as usize, y as usize) / total * bias as f64;
+            if score > best.0 {
+                best = (score, x, y);
+            }
+        }
+    }
+
+    FocalPoint {
+        x: (best.1 as f32 + window_w as f32 / 2.0) / thumb_w as f32,
+        y: (best.2 as f32 + window_h as f32 / 2.0) / thumb_h as f32,
+    }
+}
+
+// Top-left corner of a `crop_w` x `crop_h` window centered on `focal`, kept
+// inside a `width` x `height` image
+pub fn crop_origin(focal: FocalPoint, width: u32, height: u32, crop_w: u32, crop_h: u32) -> (u32, u32) {
+    let focal = focal.clamped();
+    let place = |center: f32, size: u32, window: u32| {
+        let start = center * size as f32 - window as f32 / 2.0;
+        (start.round().max(0.0) as u32).min(size.saturating_sub(window))
+    };
+    (place(focal.x, width, crop_w), place(focal.y, height, crop_h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // Flat gray 400×200 with a high-contrast checkerboard in the box
+    fn object_at(left: u32, top: u32, size: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(400, 200, |x, y| {
+            let inside = (left..left + size).contains(&x) && (top..top + size).contains(&y);
+            if inside && (x / 4 + y / 4) % 2 == 0 {
+                Rgb([0, 0, 0])
+            } else if inside {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([128, 128, 128])
+            }
+        }))
+    }
+
+    // The crop window the focal point places in the full image
+    fn window(img: &DynamicImage, crop_w: u32, crop_h: u32) -> (u32, u32) {
+        let (width, height) = img.dimensions();
+        let focal = find_focal_point(img, crop_w, crop_h);
+        crop_origin(focal, width, height, crop_w, crop_h)
+    }
+
+    #[test]
+    fn object_on_a_flat_background_ends_up_in_the_crop() {
+        for left in [10, 170, 330] {
+            let img = object_at(left, 70, 60);
+            let (x, _) = window(&img, 100, 200);
+            assert!(x <= left && left + 60 <= x + 100, "object at {}, window at {}", left, x);
+        }
+    }
+
+    #[test]
+    fn flat_image_stays_centered() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(400, 200, Rgb([90, 90, 90])));
+        assert_eq!(window(&img, 100, 200), (150, 0));
+    }
+
+    #[test]
+    fn window_covering_the_image_is_centered() {
+        let img = object_at(10, 70, 60);
+        let focal = find_focal_point(&img, 400, 200);
+        assert_eq!((focal.x, focal.y), (0.5, 0.5));
+    }
+
+    #[test]
+    fn window_is_clamped_at_the_edges() {
+        let corner = |x, y| FocalPoint { x, y };
+        assert_eq!(crop_origin(corner(0.0, 0.0), 400, 200, 100, 100), (0, 0));
+        assert_eq!(crop_origin(corner(1.0, 1.0), 400, 200, 100, 100), (300, 100));
+        assert_eq!(crop_origin(corner(-3.0, 7.0), 400, 200, 100, 100), (0, 100));
+        assert_eq!(crop_origin(corner(0.5, 0.5), 400, 200, 100, 100), (150, 50));
+        // A window larger than the image starts at the origin
+        assert_eq!(crop_origin(corner(0.9, 0.9), 400, 200, 500, 300), (0, 0));
+    }
+}
@@ -0,0 +1,191 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use resvg::tiny_skia::{Color, Pixmap, Transform};
+use resvg::usvg::{self, fontdb};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::decode::SourceHeader;
+use crate::formats::{self, SourceKind};
+use crate::resize::parse_hex_color;
+use crate::ProcessError;
+
+// SVG user units are CSS pixels at 96 DPI
+const CSS_DPI: f32 = 96.0;
+
+// Loading system fonts takes a while, so it happens once per process
+static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+
+// Raster size for SVG sources; the regular resize step still runs afterwards
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SvgOptions {
+    pub width: Option<u32>, // Raster width in pixels; with height, fits inside both keeping the aspect ratio
+    pub height: Option<u32>, // Raster height in pixels
+    pub dpi: Option<f32>, // Used when no width/height is given, default 96 (the SVG's own size)
+    pub background: Option<String>, // "#RRGGBB[AA]", default transparent
+}
+
+pub fn is_svg(path: &Path) -> bool {
+    formats::source_kind(path) == Some(SourceKind::Svg)
+}
+
+fn parse_tree(path: &Path) -> Result<usvg::Tree, String> {
+    let data = fs::read(path)
+        .map_err(|e| format!("Failed to read SVG: {}", e))?;
+
+    let fontdb = FONTS.get_or_init(|| {
+        let mut fontdb = fontdb::Database::new();
+        fontdb.load_system_fonts();
+        Arc::new(fontdb)
+    });
+    let options = usvg::Options {
+        // Relative <image> references resolve next to the SVG file
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb: fontdb.clone(),
+        ..usvg::Options::default()
+    };
+
+    usvg::Tree::from_data(&data, &options)
+        .map_err(|e| format!("Failed to parse SVG: {}", e))
+}
+
+// Scale from the SVG's own size to the requested raster size
+fn raster_scale(tree: &usvg::Tree, options: &SvgOptions) -> (f32, u32, u32) {
+    let size = tree.size();
+    let (width, height) = (size.width(), size.height());
+    let scale = match (options.width, options.height) {
+        (Some(w), Some(h)) => f32::min(w as f32 / width, h as f32 / height),
+        (Some(w), None) => w as f32 / width,
+        (None, Some(h)) => h as f32 / height,
+        (None, None) => options.dpi.unwrap_or(CSS_DPI).max(1.0) / CSS_DPI,
+    };
+    let raster_width = (width * scale).round().max(1.0) as u32;
+    let raster_height = (height * scale).round().max(1.0) as u32;
+    (scale, raster_width, raster_height)
+}
+
+// The SVG's own size in pixels, for display before processing
+pub fn intrinsic_size(path: &Path) -> Result<(u32, u32), String> {
+    let tree = parse_tree(path)?;
+    let size = tree.size().to_int_size();
+    Ok((size.width(), size.height()))
+}
+
+// Dimensions of the raster that `rasterize` would produce, so limits and the
+// memory budget apply before any pixels are allocated
+pub fn read_header(path: &Path, options: &SvgOptions) -> Result<SourceHeader, ProcessError> {
+    let tree = parse_tree(path)?;
+    let (_, width, height) = raster_scale(&tree, options);
+    // Transparent unless an opaque background is painted first
+    let has_alpha = match &options.background {
+        Some(background) => parse_hex_color(background)?.0[3] < 255,
+        None => true,
+    };
+    Ok(SourceHeader {
+        width,
+        height,
+        bytes_per_pixel: 4,
+        has_alpha,
+    })
+}
+
+pub fn rasterize(path: &Path, options: &SvgOptions) -> Result<DynamicImage, ProcessError> {
+    let tree = parse_tree(path)?;
+    let (scale, width, height) = raster_scale(&tree, options);
+
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| format!("Cannot rasterize SVG at {}×{}", width, height))?;
+    if let Some(background) = &options.background {
+        let Rgba([r, g, b, a]) = parse_hex_color(background)?;
+        pixmap.fill(Color::from_rgba8(r, g, b, a));
+    }
+    resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha
+    let pixels = pixmap.pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let rgba = RgbaImage::from_raw(width, height, pixels)
+        .ok_or("Failed to convert SVG raster")?;
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 40×20 with a half-transparent red left half and nothing on the right
+    const DRAWING: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20" viewBox="0 0 40 20">
+        <rect x="0" y="0" width="20" height="20" fill="#ff0000" fill-opacity="0.5"/>
+    </svg>"##;
+
+    fn drawing(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("optisnap-svg-{}-{}.svg", name, std::process::id()));
+        fs::write(&path, DRAWING).unwrap();
+        path
+    }
+
+    fn raster_size(options: SvgOptions) -> (u32, u32) {
+        let path = drawing("size");
+        let header = read_header(&path, &options);
+        fs::remove_file(&path).ok();
+        let header = header.unwrap();
+        (header.width, header.height)
+    }
+
+    #[test]
+    fn raster_size_follows_width_height_or_dpi() {
+        assert_eq!(raster_size(SvgOptions::default()), (40, 20));
+        assert_eq!(raster_size(SvgOptions { width: Some(80), ..Default::default() }), (80, 40));
+        assert_eq!(raster_size(SvgOptions { height: Some(10), ..Default::default() }), (20, 10));
+        // Both given: fit inside, keeping the aspect ratio
+        let both = SvgOptions { width: Some(100), height: Some(100), ..Default::default() };
+        assert_eq!(raster_size(both), (100, 50));
+        assert_eq!(raster_size(SvgOptions { dpi: Some(192.0), ..Default::default() }), (80, 40));
+        // Width wins over DPI
+        let width_and_dpi = SvgOptions { width: Some(20), dpi: Some(300.0), ..Default::default() };
+        assert_eq!(raster_size(width_and_dpi), (20, 10));
+    }
+
+    #[test]
+    fn background_is_transparent_by_default() {
+        let path = drawing("transparent");
+        let img = rasterize(&path, &SvgOptions::default());
+        let header = read_header(&path, &SvgOptions::default());
+        fs::remove_file(&path).ok();
+
+        assert_eq!(img.unwrap().to_rgba8().get_pixel(30, 10)[3], 0);
+        assert!(header.unwrap().has_alpha);
+    }
+
+    #[test]
+    fn background_color_fills_empty_areas() {
+        let path = drawing("background");
+        let options = SvgOptions { background: Some("#00ff00".to_string()), ..Default::default() };
+        let img = rasterize(&path, &options);
+        let header = read_header(&path, &options);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(img.unwrap().to_rgba8().get_pixel(30, 10).0, [0, 255, 0, 255]);
+        assert!(!header.unwrap().has_alpha);
+    }
+
+    #[test]
+    fn half_transparent_pixels_are_demultiplied() {
+        let path = drawing("demultiply");
+        let img = rasterize(&path, &SvgOptions::default());
+        fs::remove_file(&path).ok();
+
+        // Premultiplied storage would read back as roughly half red
+        let [r, g, b, a] = img.unwrap().to_rgba8().get_pixel(10, 10).0;
+        assert!(r >= 254, "red {}", r);
+        assert_eq!((g, b), (0, 0));
+        assert!((127..=128).contains(&a), "alpha {}", a);
+    }
+}
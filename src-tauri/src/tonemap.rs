@@ -0,0 +1,171 @@
+use image::{DynamicImage, Rgb32FImage, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::formats::{self, SourceKind};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ToneMapOperator {
+    #[serde(rename = "clamp")]
+    Clamp, // Clip everything above 1.0
+    #[default]
+    #[serde(rename = "reinhard")]
+    Reinhard, // Compress luminance, keeps hue and saturation
+    #[serde(rename = "aces")]
+    Aces, // Filmic curve (Narkowicz ACES fit) with a contrasty toe and soft highlight roll-off
+}
+
+impl ToneMapOperator {
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapOperator::Clamp => "clamp",
+            ToneMapOperator::Reinhard => "reinhard",
+            ToneMapOperator::Aces => "aces",
+        }
+    }
+}
+
+// Conversion of HDR sources to display-referred 0.0 - 1.0 sRGB
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ToneMapOptions {
+    pub operator: Option<ToneMapOperator>, // Default reinhard
+    pub exposure: Option<f32>, // Stops applied before the operator, e.g. -1.0 halves brightness, default 0
+    pub white_point: Option<f32>, // Reinhard: linear value that maps to pure white, default infinite
+}
+
+// Sources whose pixels are scene-linear and may exceed 1.0
+pub fn is_hdr_source(path: &Path) -> bool {
+    formats::source_kind(path) == Some(SourceKind::Hdr)
+}
+
+fn aces(x: f32) -> f32 {
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+// sRGB transfer function for a linear value in 0.0 - 1.0
+fn encode_srgb(linear: f32) -> f32 {
+    let linear = linear.clamp(0.0, 1.0);
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn map_pixel(rgb: [f32; 3], operator: ToneMapOperator, scale: f32, white_point: Option<f32>) -> [f32; 3] {
+    // Negative values are out of gamut noise from the renderer
+    let [r, g, b] = rgb.map(|v| v.max(0.0) * scale);
+    let mapped = match operator {
+        ToneMapOperator::Clamp => [r, g, b],
+        ToneMapOperator::Reinhard => {
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            if luma <= 0.0 {
+                [0.0; 3]
+            } else {
+                let compressed = match white_point {
+                    Some(white) if white > 0.0 => luma * (1.0 + luma / (white * white)) / (1.0 + luma),
+                    _ => luma / (1.0 + luma),
+                };
+                [r, g, b].map(|v| v * compressed / luma)
+            }
+        }
+        ToneMapOperator::Aces => [aces(r), aces(g), aces(b)],
+    };
+    mapped.map(encode_srgb)
+}
+
+// Map scene-linear float pixels to display sRGB in 0.0 - 1.0, still as float
+// so the usual bit-depth step decides the final precision. Alpha is kept as is.
+pub fn tone_map(img: DynamicImage, options: &ToneMapOptions) -> DynamicImage {
+    let operator = options.operator.unwrap_or_default();
+    let scale = 2f32.powf(options.exposure.unwrap_or(0.0));
+    let white_point = options.white_point;
+
+    if img.color().has_alpha() {
+        let mut rgba: Rgba32FImage = img.into_rgba32f();
+        for pixel in rgba.pixels_mut() {
+            let [r, g, b, a] = pixel.0;
+            let [r, g, b] = map_pixel([r, g, b], operator, scale, white_point);
+            pixel.0 = [r, g, b, a.clamp(0.0, 1.0)];
+        }
+        DynamicImage::ImageRgba32F(rgba)
+    } else {
+        let mut rgb: Rgb32FImage = img.into_rgb32f();
+        for pixel in rgb.pixels_mut() {
+            pixel.0 = map_pixel(pixel.0, operator, scale, white_point);
+        }
+        DynamicImage::ImageRgb32F(rgb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    fn gray(value: f32, operator: ToneMapOperator, exposure: f32, white_point: Option<f32>) -> f32 {
+        map_pixel([value; 3], operator, 2f32.powf(exposure), white_point)[0]
+    }
+
+    #[test]
+    fn exposure_scales_by_powers_of_two() {
+        let options = |exposure| ToneMapOptions {
+            operator: Some(ToneMapOperator::Clamp),
+            exposure: Some(exposure),
+            white_point: None,
+        };
+        let img = DynamicImage::ImageRgb32F(Rgb32FImage::from_pixel(1, 1, image::Rgb([0.25; 3])));
+
+        let brighter = tone_map(img.clone(), &options(1.0)).into_rgb32f();
+        assert!(close(brighter.get_pixel(0, 0)[0], encode_srgb(0.5)));
+        let darker = tone_map(img, &options(-2.0)).into_rgb32f();
+        assert!(close(darker.get_pixel(0, 0)[0], encode_srgb(0.0625)));
+    }
+
+    #[test]
+    fn reinhard_maps_one_to_half() {
+        assert!(close(gray(1.0, ToneMapOperator::Reinhard, 0.0, None), encode_srgb(0.5)));
+    }
+
+    #[test]
+    fn reinhard_reaches_white_at_the_white_point() {
+        assert!(close(gray(4.0, ToneMapOperator::Reinhard, 0.0, Some(4.0)), 1.0));
+        assert!(gray(2.0, ToneMapOperator::Reinhard, 0.0, Some(4.0)) < 1.0);
+        // Without a white point, white is only approached
+        assert!(gray(4.0, ToneMapOperator::Reinhard, 0.0, None) < 1.0);
+    }
+
+    #[test]
+    fn aces_is_monotonic_and_in_range() {
+        let mut previous = 0.0;
+        for i in 0..=2000 {
+            let mapped = gray(i as f32 * 0.01, ToneMapOperator::Aces, 0.0, None);
+            assert!((0.0..=1.0).contains(&mapped), "{} at {}", mapped, i);
+            assert!(mapped >= previous, "{} < {} at {}", mapped, previous, i);
+            previous = mapped;
+        }
+    }
+
+    #[test]
+    fn negative_inputs_clamp_to_black() {
+        for operator in [ToneMapOperator::Clamp, ToneMapOperator::Reinhard, ToneMapOperator::Aces] {
+            assert_eq!(map_pixel([-1.0, -0.5, 0.0], operator, 1.0, None), [0.0; 3], "{:?}", operator);
+        }
+    }
+
+    #[test]
+    fn alpha_is_preserved() {
+        let img = DynamicImage::ImageRgba32F(Rgba32FImage::from_fn(2, 1, |x, _| {
+            Rgba([3.0, 1.0, 0.5, if x == 0 { 0.25 } else { 1.5 }])
+        }));
+        let mapped = tone_map(img, &ToneMapOptions::default()).into_rgba32f();
+        assert_eq!(mapped.get_pixel(0, 0)[3], 0.25);
+        // Out of range alpha is clamped like the colors
+        assert_eq!(mapped.get_pixel(1, 0)[3], 1.0);
+        assert!(mapped.get_pixel(0, 0)[0] <= 1.0);
+    }
+}
@@ -0,0 +1,353 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{Emitter, Manager, State};
+
+use crate::cache::{self, ProcessingCache};
+use crate::memory::MemoryBudget;
+use crate::formats;
+use crate::{process_file, FileStatus, ProcessingSettings, ProgressUpdate};
+
+// How often pending files are re-checked while no new events arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const DEFAULT_DEBOUNCE_MS: u64 = 1500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchRequest {
+    pub directories: Vec<String>,
+    pub output_dir: String,
+    pub overwrite: bool,
+    #[serde(flatten)]
+    pub settings: ProcessingSettings,
+    pub recursive: Option<bool>, // Watch subdirectories too, default true
+    pub debounce_ms: Option<u64>, // Quiet period before a file counts as fully written, default 1500
+    pub use_cache: Option<bool>, // Skip files whose output is already up-to-date, default true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchInfo {
+    pub id: u64,
+    pub directories: Vec<String>,
+    pub output_dir: String,
+}
+
+struct ActiveWatch {
+    request: WatchRequest,
+    stop_flag: Arc<AtomicBool>,
+    // Dropping the watcher closes the event channel, which ends the worker thread
+    _watcher: RecommendedWatcher,
+}
+
+#[derive(Default)]
+pub struct WatchRegistry {
+    next_id: AtomicU64,
+    watches: Mutex<HashMap<u64, ActiveWatch>>,
+}
+
+// Size and modification time of a file, see `cache::file_stamp`
+type FileStamp = (u64, SystemTime);
+
+struct PendingFile {
+    last_change: Instant,
+    last_size: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Readiness {
+    Waiting,
+    Ready,
+    Gone, // Deleted or renamed before it settled
+}
+
+impl PendingFile {
+    fn new(now: Instant) -> Self {
+        PendingFile {
+            last_change: now,
+            last_size: None,
+        }
+    }
+
+    // A file is ready once it has been quiet for the debounce period and its
+    // size did not change across two consecutive checks. `size` is None when
+    // the file no longer exists.
+    fn check(&mut self, now: Instant, debounce: Duration, size: Option<u64>) -> Readiness {
+        if now.duration_since(self.last_change) < debounce {
+            return Readiness::Waiting;
+        }
+        let Some(size) = size else {
+            return Readiness::Gone;
+        };
+        if self.last_size == Some(size) {
+            Readiness::Ready
+        } else {
+            self.last_size = Some(size);
+            self.last_change = now;
+            Readiness::Waiting
+        }
+    }
+}
+
+// Whether `path` is an output we wrote that has not changed since. Entries
+// whose stamp no longer matches are dropped, so the map only holds live outputs.
+fn is_own_output(written: &mut HashMap<PathBuf, FileStamp>, path: &Path, stamp: Option<FileStamp>) -> bool {
+    match written.get(path) {
+        Some(ours) if stamp.as_ref() == Some(ours) => true,
+        Some(_) => {
+            written.remove(path);
+            false
+        }
+        None => false,
+    }
+}
+
+fn is_watchable(path: &Path) -> bool {
+    // Never pick up our own backups
+    let in_backup_dir = path.components()
+        .any(|c| c.as_os_str() == ".optisnap_backups");
+    !in_backup_dir && formats::is_supported_input(path)
+}
+
+fn run_watch_worker(
+    app: tauri::AppHandle,
+    request: WatchRequest,
+    events: mpsc::Receiver<notify::Result<Event>>,
+    stop_flag: Arc<AtomicBool>,
+) {
+    let debounce = Duration::from_millis(request.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS));
+    let use_cache = request.use_cache.unwrap_or(true);
+
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+    // Outputs we wrote, with the stamp they had, so our own writes don't retrigger
+    let mut written: HashMap<PathBuf, FileStamp> = HashMap::new();
+
+    let mut processed = 0;
+    let mut success_count = 0;
+    let mut failed_count = 0;
+    let mut cached_count = 0;
+
+    loop {
+        if stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+
+        match events.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any) {
+                    for path in event.paths {
+                        if is_watchable(&path) {
+                            pending.insert(path, PendingFile::new(Instant::now()));
+                        }
+                    }
+                }
+            }
+            // Watcher errors (e.g. a dropped event queue) only affect single events
+            Ok(Err(_)) => {}
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        let mut ready = Vec::new();
+        pending.retain(|path, file| {
+            let size = fs::metadata(path).ok().map(|metadata| metadata.len());
+            match file.check(now, debounce, size) {
+                Readiness::Waiting => true,
+                Readiness::Ready => {
+                    ready.push(path.clone());
+                    false
+                }
+                Readiness::Gone => false,
+            }
+        });
+
+        let cache = app.state::<ProcessingCache>();
+        let mut batch_processed = false;
+        let batch_size = ready.len();
+        for (index, path) in ready.into_iter().enumerate() {
+            if stop_flag.load(Ordering::Relaxed) {
+                break;
+            }
+            if is_own_output(&mut written, &path, cache::file_stamp(&path)) {
+                continue;
+            }
+
+            let path_str = path.to_string_lossy().to_string();
+            // This file, the rest of this batch, and whatever is still settling
+            let total = processed + (batch_size - index) + pending.len();
+
+            let _ = app.emit("progress-update", ProgressUpdate {
+                current: processed,
+                total,
+                success_count,
+                failed_count,
+                cached_count,
+                current_file: Some(path_str.clone()),
+            });
+
+            let budget = app.state::<MemoryBudget>();
+            let result = process_file(
+                &path_str,
+                &request.output_dir,
+                request.overwrite,
+                &request.settings,
+                None,
+                use_cache.then_some(&*cache),
+                &budget,
+            );
+
+            match result.status {
+                FileStatus::Cached => cached_count += 1,
+                FileStatus::Failed => failed_count += 1,
+                _ => success_count += 1,
+            }
+            processed += 1;
+            batch_processed = true;
+
+            if let Some(output_path) = &result.output_path {
+                let output_path = PathBuf::from(output_path);
+                if let Some(stamp) = cache::file_stamp(&output_path) {
+                    written.insert(output_path, stamp);
+                }
+            }
+
+            let _ = app.emit("file-result", &result);
+            let _ = app.emit("progress-update", ProgressUpdate {
+                current: processed,
+                total,
+                success_count,
+                failed_count,
+                cached_count,
+                current_file: None,
+            });
+        }
+
+        // One cache write per batch rather than per file. A stop request only
+        // breaks out of the batch above, so this also covers stopping mid-batch.
+        if use_cache && batch_processed {
+            let _ = cache.save();
+        }
+    }
+}
+
+#[tauri::command]
+pub fn start_watch(
+    request: WatchRequest,
+    app: tauri::AppHandle,
+    registry: State<WatchRegistry>,
+) -> Result<u64, String> {
+    if request.directories.is_empty() {
+        return Err("No directories to watch".to_string());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    let mode = if request.recursive.unwrap_or(true) {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    for dir in &request.directories {
+        watcher.watch(Path::new(dir), mode)
+            .map_err(|e| format!("Failed to watch {}: {}", dir, e))?;
+    }
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let worker_request = request.clone();
+    let worker_stop_flag = stop_flag.clone();
+    std::thread::spawn(move || {
+        run_watch_worker(app, worker_request, receiver, worker_stop_flag);
+    });
+
+    let id = registry.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    registry.watches.lock()
+        .map_err(|_| "Watch registry lock poisoned".to_string())?
+        .insert(id, ActiveWatch {
+            request,
+            stop_flag,
+            _watcher: watcher,
+        });
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn stop_watch(id: u64, registry: State<WatchRegistry>) -> Result<String, String> {
+    let watch = registry.watches.lock()
+        .map_err(|_| "Watch registry lock poisoned".to_string())?
+        .remove(&id)
+        .ok_or_else(|| format!("No active watch with id {}", id))?;
+
+    watch.stop_flag.store(true, Ordering::Relaxed);
+    Ok(format!("Stopped watch {}", id))
+}
+
+#[tauri::command]
+pub fn list_watches(registry: State<WatchRegistry>) -> Result<Vec<WatchInfo>, String> {
+    let watches = registry.watches.lock()
+        .map_err(|_| "Watch registry lock poisoned".to_string())?;
+
+    let mut infos: Vec<WatchInfo> = watches.iter()
+        .map(|(id, watch)| WatchInfo {
+            id: *id,
+            directories: watch.request.directories.clone(),
+            output_dir: watch.request.output_dir.clone(),
+        })
+        .collect();
+    infos.sort_by_key(|info| info.id);
+
+    Ok(infos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+    #[test]
+    fn growing_file_waits_until_its_size_settles() {
+        let start = Instant::now();
+        let mut file = PendingFile::new(start);
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(file.check(at(1000), DEBOUNCE, Some(100)), Readiness::Waiting);
+        // Quiet long enough, but the size is only seen once
+        assert_eq!(file.check(at(1500), DEBOUNCE, Some(100)), Readiness::Waiting);
+        // Still growing, so the quiet period starts over
+        assert_eq!(file.check(at(3000), DEBOUNCE, Some(200)), Readiness::Waiting);
+        assert_eq!(file.check(at(4000), DEBOUNCE, Some(200)), Readiness::Waiting);
+        assert_eq!(file.check(at(4500), DEBOUNCE, Some(200)), Readiness::Ready);
+    }
+
+    #[test]
+    fn deleted_file_is_dropped() {
+        let start = Instant::now();
+        let mut file = PendingFile::new(start);
+        assert_eq!(file.check(start + DEBOUNCE, DEBOUNCE, Some(100)), Readiness::Waiting);
+        assert_eq!(file.check(start + DEBOUNCE * 2, DEBOUNCE, None), Readiness::Gone);
+    }
+
+    #[test]
+    fn own_outputs_are_ignored_until_they_change() {
+        let path = PathBuf::from("out/photo_optimized.jpg");
+        let stamp = (1234, SystemTime::UNIX_EPOCH + Duration::from_nanos(1_500_000_001));
+        let mut written = HashMap::from([(path.clone(), stamp)]);
+
+        assert!(is_own_output(&mut written, &path, Some(stamp)));
+        assert!(!is_own_output(&mut written, Path::new("in/photo.jpg"), Some(stamp)));
+        assert_eq!(written.len(), 1);
+
+        // Rewritten by someone else in the same second: process it and forget our stamp
+        let changed = (1234, stamp.1 + Duration::from_millis(1));
+        assert!(!is_own_output(&mut written, &path, Some(changed)));
+        assert!(written.is_empty());
+    }
+}
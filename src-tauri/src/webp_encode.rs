@@ -0,0 +1,195 @@
+use image::DynamicImage;
+use libwebp_sys::{WebPConfigLosslessPreset, WebPPreset};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use webp::WebPConfig;
+
+const DEFAULT_LOSSLESS_LEVEL: u8 = 6;
+
+// Content hint that tunes libwebp's defaults (filtering, spatial noise shaping)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum WebpPreset {
+    #[default]
+    #[serde(rename = "default")]
+    Default,
+    #[serde(rename = "picture")]
+    Picture, // Indoor photos, portraits
+    #[serde(rename = "photo")]
+    Photo, // Outdoor photos with natural lighting
+    #[serde(rename = "drawing")]
+    Drawing, // Hand or line drawings with high-contrast details
+    #[serde(rename = "icon")]
+    Icon, // Small colorful images
+    #[serde(rename = "text")]
+    Text, // Text-like content
+}
+
+impl WebpPreset {
+    fn to_libwebp(self) -> WebPPreset {
+        match self {
+            WebpPreset::Default => WebPPreset::WEBP_PRESET_DEFAULT,
+            WebpPreset::Picture => WebPPreset::WEBP_PRESET_PICTURE,
+            WebpPreset::Photo => WebPPreset::WEBP_PRESET_PHOTO,
+            WebpPreset::Drawing => WebPPreset::WEBP_PRESET_DRAWING,
+            WebpPreset::Icon => WebPPreset::WEBP_PRESET_ICON,
+            WebpPreset::Text => WebPPreset::WEBP_PRESET_TEXT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebpOptions {
+    pub preset: Option<WebpPreset>, // Default default
+    pub method: Option<u8>, // Lossy effort 0 (fast) - 6 (smallest), default 4
+    pub lossless: Option<bool>, // Lossless output in optimizing modes too, default false
+    pub lossless_level: Option<u8>, // Lossless effort 0 (fast) - 9 (smallest), default 6
+    pub near_lossless: Option<u8>, // 0 - 100, lower allows more preprocessing; implies lossless
+    pub alpha_quality: Option<u8>, // 0 - 100, default 100
+    pub sharp_yuv: Option<bool>, // Sharper, more accurate RGB to YUV conversion, default false
+    pub filter_strength: Option<u8>, // Deblocking filter 0 (off) - 100, default from preset
+    pub target_size: Option<u32>, // Target output size in bytes; quality is searched to hit it
+}
+
+impl WebpOptions {
+    fn wants_lossless(&self) -> bool {
+        self.lossless.unwrap_or(false) || self.near_lossless.is_some()
+    }
+}
+
+fn build_config(quality: f32, lossless: bool, options: &WebpOptions) -> Result<WebPConfig, String> {
+    let preset = options.preset.unwrap_or_default().to_libwebp();
+    let mut config = WebPConfig::new_with_preset(preset, quality)
+        .map_err(|_| "Failed to initialize WebP config".to_string())?;
+
+    if lossless {
+        let level = options.lossless_level.unwrap_or(DEFAULT_LOSSLESS_LEVEL).min(9);
+        // Sets lossless, method and quality together for the given effort level
+        if unsafe { WebPConfigLosslessPreset(&mut config, level as i32) } == 0 {
+            return Err("Failed to initialize lossless WebP config".to_string());
+        }
+        if let Some(near_lossless) = options.near_lossless {
+            config.near_lossless = near_lossless.min(100) as i32;
+        }
+    } else {
+        config.alpha_compression = 1;
+        if let Some(method) = options.method {
+            config.method = method.min(6) as i32;
+        }
+        if let Some(filter_strength) = options.filter_strength {
+            config.filter_strength = filter_strength.min(100) as i32;
+        }
+        if let Some(target_size) = options.target_size {
+            config.target_size = target_size.min(i32::MAX as u32) as i32;
+            // Several passes are needed to converge on a target size
+            config.pass = config.pass.max(6);
+        }
+    }
+
+    if let Some(alpha_quality) = options.alpha_quality {
+        config.alpha_quality = alpha_quality.min(100) as i32;
+    }
+    if let Some(sharp_yuv) = options.sharp_yuv {
+        config.use_sharp_yuv = sharp_yuv as i32;
+    }
+
+    Ok(config)
+}
+
+fn encode(img: &DynamicImage, config: &WebPConfig) -> Result<Vec<u8>, String> {
+    // Images without alpha skip the alpha plane entirely
+    let webp_data = if img.color().has_alpha() {
+        let rgba = img.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        webp::Encoder::from_rgba(&rgba, width, height).encode_advanced(config)
+    } else {
+        let rgb = img.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        webp::Encoder::from_rgb(&rgb, width, height).encode_advanced(config)
+    };
+    let webp_data = webp_data.map_err(|e| format!("Failed to encode WebP: {:?}", e))?;
+    Ok(webp_data.to_vec())
+}
+
+// Lossy unless the options ask for lossless or near-lossless output
+pub fn save_webp(img: &DynamicImage, output_path: &Path, quality: f32, options: &WebpOptions) -> Result<(), String> {
+    let config = build_config(quality, options.wants_lossless(), options)?;
+    let webp_data = encode(img, &config)?;
+
+    fs::write(output_path, webp_data)
+        .map_err(|e| format!("Failed to write WebP file: {}", e))
+}
+
+pub fn save_webp_lossless(img: &DynamicImage, output_path: &Path, options: &WebpOptions) -> Result<(), String> {
+    let config = build_config(75.0, true, options)?;
+    let webp_data = encode(img, &config)?;
+
+    fs::write(output_path, webp_data)
+        .map_err(|e| format!("Failed to write WebP file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn config(options: WebpOptions) -> WebPConfig {
+        build_config(75.0, options.wants_lossless(), &options).unwrap()
+    }
+
+    #[test]
+    fn preset_tunes_the_lossy_config() {
+        let photo = config(WebpOptions { preset: Some(WebpPreset::Photo), ..Default::default() });
+        assert_eq!((photo.sns_strength, photo.filter_strength), (80, 30));
+        let text = config(WebpOptions { preset: Some(WebpPreset::Text), ..Default::default() });
+        assert_eq!((text.sns_strength, text.segments), (0, 2));
+        // Explicit settings win over the preset
+        let filtered = config(WebpOptions {
+            preset: Some(WebpPreset::Text),
+            filter_strength: Some(40),
+            ..Default::default()
+        });
+        assert_eq!(filtered.filter_strength, 40);
+    }
+
+    #[test]
+    fn method_is_clamped_to_six() {
+        assert_eq!(config(WebpOptions::default()).method, 4);
+        assert_eq!(config(WebpOptions { method: Some(2), ..Default::default() }).method, 2);
+        assert_eq!(config(WebpOptions { method: Some(9), ..Default::default() }).method, 6);
+    }
+
+    #[test]
+    fn near_lossless_implies_lossless() {
+        let near = config(WebpOptions { near_lossless: Some(60), ..Default::default() });
+        assert_eq!((near.lossless, near.near_lossless), (1, 60));
+        let capped = config(WebpOptions { near_lossless: Some(150), ..Default::default() });
+        assert_eq!(capped.near_lossless, 100);
+        // Lossless effort picks method and quality together
+        let level = config(WebpOptions { lossless: Some(true), lossless_level: Some(9), ..Default::default() });
+        assert_eq!((level.lossless, level.method, level.quality), (1, 6, 100.0));
+    }
+
+    #[test]
+    fn target_size_searches_over_several_passes() {
+        let sized = config(WebpOptions { target_size: Some(5000), ..Default::default() });
+        assert_eq!(sized.target_size, 5000);
+        assert!(sized.pass >= 6);
+        assert_eq!(config(WebpOptions::default()).target_size, 0);
+    }
+
+    #[test]
+    fn lossless_option_keeps_exact_pixels_when_optimizing() {
+        // Alpha stays above zero: libwebp may rewrite the colors of fully clear pixels
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(48, 32, |x, y| {
+            Rgba([(x * 5) as u8, (y * 7) as u8, ((x * y) % 256) as u8, (64 + x * 3) as u8])
+        }));
+        let path = std::env::temp_dir().join(format!("optisnap-webp-{}.webp", std::process::id()));
+        let options = WebpOptions { lossless: Some(true), ..Default::default() };
+        save_webp(&img, &path, 40.0, &options).unwrap();
+        let decoded = image::open(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(decoded.unwrap().to_rgba8(), img.to_rgba8());
+    }
+}
@@ -12,13 +12,16 @@ import {
 import { open, message } from '@tauri-apps/plugin-dialog';
 import { stat } from '@tauri-apps/plugin-fs';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { useTranslation } from 'react-i18next';
 import {
    TrackedFile,
-   SUPPORTED_EXTENSIONS,
    FileStatus,
    OperationMode,
+   ScannedFile,
+   ScanProgress,
+   ScanResult,
 } from '@/types';
 import { Button } from '@/components/ui/button';
 import { cn } from '@/lib/utils';
@@ -40,10 +43,11 @@ function formatFileSize(bytes: number): string {
    return parseFloat((bytes / Math.pow(k, i)).toFixed(1)) + ' ' + sizes[i];
 }
 
-function isValidImageFile(filename: string): boolean {
-   const ext = filename.toLowerCase().slice(filename.lastIndexOf('.'));
-   return SUPPORTED_EXTENSIONS.includes(
-      ext as (typeof SUPPORTED_EXTENSIONS)[number],
+// `extensions` come from the backend format registry, without the dot
+function isValidImageFile(filename: string, extensions: string[]): boolean {
+   const dot = filename.lastIndexOf('.');
+   return (
+      dot >= 0 && extensions.includes(filename.slice(dot + 1).toLowerCase())
    );
 }
 
@@ -51,6 +55,19 @@ function generateId(): string {
    return Math.random().toString(36).substring(2, 11);
 }
 
+// Scanned files already carry size and dimensions, so no extra stat is needed
+function scannedToTracked(file: ScannedFile): TrackedFile {
+   return {
+      id: generateId(),
+      name: file.path.split(/[\\/]/).pop() || file.path,
+      path: file.path,
+      size: file.size,
+      width: file.width ?? undefined,
+      height: file.height ?? undefined,
+      status: 'pending',
+   };
+}
+
 function getStatusIcon(status: FileStatus) {
    switch (status) {
       case 'pending':
@@ -58,6 +75,7 @@ function getStatusIcon(status: FileStatus) {
       case 'processing':
          return <Loader2 className="w-4 h-4 text-primary animate-spin" />;
       case 'success':
+      case 'cached':
          return <CheckCircle2 className="w-4 h-4 text-emerald-600" />;
       case 'failed':
          return <AlertCircle className="w-4 h-4 text-red-600" />;
@@ -79,6 +97,7 @@ function getStatusText(
             </span>
          );
       case 'success':
+      case 'cached':
          if (originalSize && outputSize) {
             const reduction = (
                ((originalSize - outputSize) / originalSize) *
@@ -118,6 +137,80 @@ export function DropZone({
       current: 0,
       total: 0,
    });
+   const [inputExtensions, setInputExtensions] = useState<string[]>([]);
+   const [scanStatus, setScanStatus] = useState<{
+      found: number;
+      dirs: number;
+      currentDir: string | null;
+   } | null>(null);
+
+   // Scan a folder, adding files to the list as each `scan-progress` batch
+   // arrives. Every file in the returned result has already been added.
+   const scanFolder = useCallback(
+      async (folderPath: string): Promise<ScanResult> => {
+         setScanStatus({ found: 0, dirs: 0, currentDir: null });
+
+         // Events and the command result are not ordered relative to each
+         // other, so track what was added to avoid losing or repeating files
+         const added = new Set<string>();
+         const addScanned = (scanned: ScannedFile[]) => {
+            const fresh = scanned.filter((f) => !added.has(f.path));
+            fresh.forEach((f) => added.add(f.path));
+            if (fresh.length > 0) {
+               onFilesAdded(fresh.map(scannedToTracked));
+            }
+         };
+
+         const unlisten = await listen<ScanProgress>(
+            'scan-progress',
+            (event) => {
+               addScanned(event.payload.files);
+               setScanStatus({
+                  found: event.payload.found_count,
+                  dirs: event.payload.scanned_dirs,
+                  currentDir: event.payload.current_dir,
+               });
+            },
+         );
+
+         try {
+            const scan = await invoke<ScanResult>('scan_folder_for_images', {
+               folderPath,
+            });
+            addScanned(scan.files);
+
+            if (scan.errors.length > 0) {
+               console.warn('Folder scan errors:', scan.errors);
+               await message(
+                  t('dropzone.scanErrors', {
+                     count: scan.errors.length,
+                     path: scan.errors[0].path,
+                     error: scan.errors[0].error,
+                  }),
+                  { title: t('dropzone.scanErrorsTitle'), kind: 'warning' },
+               );
+            }
+            return scan;
+         } finally {
+            unlisten();
+            setScanStatus(null);
+         }
+      },
+      [onFilesAdded, t],
+   );
+
+   const handleCancelScan = useCallback(() => {
+      invoke('cancel_scan').catch((e) =>
+         console.error('Failed to cancel scan:', e),
+      );
+   }, []);
+
+   // Readable formats depend on the backend build (e.g. the heif feature)
+   useEffect(() => {
+      invoke<string[]>('get_supported_input_extensions')
+         .then(setInputExtensions)
+         .catch((e) => console.error('Failed to get supported formats:', e));
+   }, []);
 
    // Process file paths and add to tracked files
    const processFilePaths = useCallback(
@@ -139,7 +232,7 @@ export function DropZone({
                   batch.map(async (path): Promise<TrackedFile | null> => {
                      const name = path.split(/[\\/]/).pop() || path;
 
-                     if (isValidImageFile(name)) {
+                     if (isValidImageFile(name, inputExtensions)) {
                         try {
                            const fileInfo = await stat(path);
                            let dimensions = undefined;
@@ -194,7 +287,7 @@ export function DropZone({
             setLoadingProgress({ current: 0, total: 0 });
          }
       },
-      [disabled, onFilesAdded],
+      [disabled, onFilesAdded, inputExtensions],
    );
 
    // Use Tauri's drag-drop event listener
@@ -207,32 +300,34 @@ export function DropZone({
             setIsDragging(false);
             if (event.payload.paths && event.payload.paths.length > 0) {
                const paths = event.payload.paths;
-               const expandedPaths: string[] = [];
+               const filePaths: string[] = [];
 
-               // Check if paths contain folders and expand them
+               // Folders are scanned and added as they stream in
                for (const path of paths) {
+                  let isDirectory = false;
                   try {
-                     const fileInfo = await stat(path);
-                     if (fileInfo.isDirectory) {
-                        // Scan folder for images
-                        const imagePaths = await invoke<string[]>(
-                           'scan_folder_for_images',
-                           {
-                              folderPath: path,
-                           },
-                        );
-                        expandedPaths.push(...imagePaths);
-                     } else {
-                        expandedPaths.push(path);
-                     }
+                     isDirectory = (await stat(path)).isDirectory;
                   } catch (e) {
                      // If stat fails, assume it's a file
-                     expandedPaths.push(path);
+                  }
+
+                  if (!isDirectory) {
+                     filePaths.push(path);
+                     continue;
+                  }
+                  try {
+                     await scanFolder(path);
+                  } catch (error) {
+                     console.error('Failed to scan folder:', error);
+                     await message(t('dropzone.folderError'), {
+                        title: t('common.error'),
+                        kind: 'error',
+                     });
                   }
                }
 
-               if (expandedPaths.length > 0) {
-                  processFilePaths(expandedPaths);
+               if (filePaths.length > 0) {
+                  processFilePaths(filePaths);
                }
             }
          } else if (event.payload.type === 'leave') {
@@ -243,7 +338,7 @@ export function DropZone({
       return () => {
          unlisten.then((f) => f());
       };
-   }, [processFilePaths]);
+   }, [processFilePaths, scanFolder, t]);
 
    const handleDrop = useCallback((e: React.DragEvent<HTMLDivElement>) => {
       e.preventDefault();
@@ -276,17 +371,7 @@ export function DropZone({
          filters: [
             {
                name: 'Images',
-               extensions: [
-                  'png',
-                  'jpg',
-                  'jpeg',
-                  'webp',
-                  'tiff',
-                  'tif',
-                  'bmp',
-                  'qoi',
-                  'gif',
-               ],
+               extensions: inputExtensions,
             },
          ],
       });
@@ -295,7 +380,7 @@ export function DropZone({
 
       const paths = Array.isArray(selected) ? selected : [selected];
       await processFilePaths(paths);
-   }, [disabled, processFilePaths, isLoadingFiles]);
+   }, [disabled, processFilePaths, isLoadingFiles, inputExtensions]);
 
    const handleFolderInput = useCallback(async () => {
       if (disabled || isLoadingFiles) return;
@@ -307,31 +392,23 @@ export function DropZone({
 
       if (!selected || typeof selected !== 'string') return;
 
-      setIsLoadingFiles(true);
       try {
-         const imagePaths = await invoke<string[]>('scan_folder_for_images', {
-            folderPath: selected,
-         });
+         const scan = await scanFolder(selected);
 
-         if (imagePaths.length === 0) {
+         if (!scan.cancelled && scan.files.length === 0) {
             await message(t('dropzone.noImages'), {
                title: t('dropzone.noImagesTitle'),
                kind: 'info',
             });
-            return;
          }
-
-         await processFilePaths(imagePaths);
       } catch (error) {
          console.error('Failed to scan folder:', error);
          await message(t('dropzone.folderError'), {
             title: t('common.error'),
             kind: 'error',
          });
-      } finally {
-         setIsLoadingFiles(false);
       }
-   }, [disabled, processFilePaths, isLoadingFiles]);
+   }, [disabled, scanFolder, isLoadingFiles, t]);
 
    const totalSize = files.reduce((acc, f) => acc + f.size, 0);
    const showDimensions =
@@ -378,6 +455,43 @@ export function DropZone({
             </div>
          )}
 
+         {/* Folder Scan Overlay */}
+         {scanStatus && (
+            <div className="absolute inset-0 z-50 flex items-center justify-center bg-background/80 backdrop-blur-sm">
+               <div className="bg-card border border-border rounded-lg shadow-lg p-6 min-w-75 max-w-md">
+                  <div className="flex items-center gap-3 mb-4">
+                     <Loader2 className="w-5 h-5 text-primary animate-spin" />
+                     <span className="text-sm font-medium">
+                        {t('dropzone.scanning')}
+                     </span>
+                  </div>
+                  <div className="space-y-1 text-xs text-muted-foreground">
+                     <div>
+                        {t('dropzone.scanFound', {
+                           found: scanStatus.found,
+                           dirs: scanStatus.dirs,
+                        })}
+                     </div>
+                     {scanStatus.currentDir && (
+                        <div className="truncate" title={scanStatus.currentDir}>
+                           {scanStatus.currentDir}
+                        </div>
+                     )}
+                  </div>
+                  <div className="flex justify-end mt-4">
+                     <Button
+                        variant="outline"
+                        size="sm"
+                        onClick={handleCancelScan}
+                        className="text-xs"
+                     >
+                        {t('common.cancel')}
+                     </Button>
+                  </div>
+               </div>
+            </div>
+         )}
+
          {/* Drop Zone Area - Compact */}
          {files.length === 0 ? (
             <div
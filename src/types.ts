@@ -1,5 +1,16 @@
 // Output formats supported by the optimizer
-export type OutputFormat = 'png' | 'webp' | 'jpeg' | 'tiff' | 'qoi' | 'bmp';
+export type OutputFormat =
+   | 'png'
+   | 'webp'
+   | 'jpeg'
+   | 'tiff'
+   | 'qoi'
+   | 'bmp'
+   | 'tga'
+   | 'pnm'
+   | 'ico'
+   | 'gif'
+   | 'avif';
 
 // Operation modes
 export type OperationMode =
@@ -10,16 +21,142 @@ export type OperationMode =
    | 'all';
 
 // Resize modes
-export type ResizeMode = 'dimensions' | 'percentage';
+export type ResizeMode =
+   | 'dimensions'
+   | 'percentage'
+   | 'cover' // Scale and center crop to exactly max_width x max_height
+   | 'contain' // Scale to fit and pad to exactly max_width x max_height
+   | 'long_edge' // Longest side becomes resize_length
+   | 'short_edge' // Shortest side becomes resize_length
+   | 'width' // Constrain width only
+   | 'height'; // Constrain height only
+
+// Resampling filters, fastest to sharpest
+export type ResizeFilter =
+   | 'nearest'
+   | 'triangle'
+   | 'catmull_rom'
+   | 'gaussian'
+   | 'lanczos3';
+
+// Resampling implementation; 'fast' uses SIMD and JPEG shrink-on-load
+export type ResizeBackend = 'standard' | 'fast';
+
+// Unsharp mask settings
+export interface SharpenOptions {
+   amount: number; // e.g. 0.5 adds 50% of the detail back
+   radius: number; // Gaussian sigma in pixels
+   threshold?: number; // 0-255, minimum difference before sharpening
+}
+
+// JPEG encoder implementation; 'mozjpeg' gives smaller files but is slower
+export type JpegBackend = 'standard' | 'mozjpeg';
+
+// JPEG chroma subsampling: 4:4:4, 4:2:2 or 4:2:0
+export type ChromaSubsampling = '444' | '422' | '420';
+
+// Metadata dropped by lossless JPEG optimization: 'safe' keeps EXIF and the ICC profile
+export type MetadataStrip = 'none' | 'safe' | 'all';
+
+// JPEG encoder options
+export interface JpegOptions {
+   encoder?: JpegBackend; // Default 'standard'
+   progressive?: boolean; // Default false for 'standard', true for 'mozjpeg'
+   chroma_subsampling?: ChromaSubsampling; // Default '420' ('standard': '444' from quality 90)
+   optimize_huffman?: boolean; // Default true
+   restart_interval?: number; // MCUs between restart markers, 'standard' only
+   lossless?: boolean; // 'optimize' mode: rewrite JPEG sources without re-encoding, default false
+   strip_metadata?: MetadataStrip; // Lossless mode only, default 'safe'
+}
+
+// Content hint that tunes the WebP encoder
+export type WebpPreset = 'default' | 'picture' | 'photo' | 'drawing' | 'icon' | 'text';
+
+// WebP encoder options
+export interface WebpOptions {
+   preset?: WebpPreset; // Default 'default'
+   method?: number; // Lossy effort 0 (fast) - 6 (smallest), default 4
+   lossless?: boolean; // Lossless output in optimizing modes too, default false
+   lossless_level?: number; // Lossless effort 0 (fast) - 9 (smallest), default 6
+   near_lossless?: number; // 0-100, lower allows more preprocessing; implies lossless
+   alpha_quality?: number; // 0-100, default 100
+   sharp_yuv?: boolean; // Sharper RGB to YUV conversion, default false
+   filter_strength?: number; // Deblocking filter 0 (off) - 100
+   target_size?: number; // Target output size in bytes
+}
+
+// Deflate implementation used by oxipng
+export type PngDeflater = 'libdeflate' | 'zopfli';
+
+// Ancillary PNG chunks removed by oxipng
+export type PngStrip = 'none' | 'safe' | 'all';
+
+// pngquant settings for lossy PNG output
+export interface QuantizeOptions {
+   speed?: number; // 1 (best, slowest) - 10 (fastest), default 5
+   dithering?: number; // 0 (off, best for pixel art) - 1, default 1
+   min_quality?: number; // 0-100, palettes below this are rejected, default 0
+   max_colors?: number; // 2-256, default 256
+   fallback_lossless?: boolean; // Write lossless truecolor when min_quality can't be met, default true
+}
+
+// PNG encoder and oxipng options
+export interface PngOptions {
+   lossless?: boolean; // 'optimize' mode: keep every pixel and only recompress, default false
+   level?: number; // oxipng effort 0-6, default 6 after quantization, 2 otherwise
+   deflater?: PngDeflater; // Default 'libdeflate'
+   zopfli_iterations?: number; // Default 15
+   interlace?: boolean; // Adam7 interlacing; default keeps the source's
+   strip?: PngStrip; // Default 'safe'
+   quantize?: QuantizeOptions; // Palette quantization in optimizing modes
+}
+
+// Raster size for SVG sources, before the regular resize step
+export interface SvgOptions {
+   width?: number; // Raster width; with height, fits inside both keeping the aspect ratio
+   height?: number; // Raster height
+   dpi?: number; // Used when no width/height is given, default 96 (the SVG's own size)
+   background?: string; // '#RRGGBB[AA]', default transparent
+}
+
+// Tone mapping operator for HDR sources
+export type ToneMapOperator = 'clamp' | 'reinhard' | 'aces';
+
+// Conversion of EXR / Radiance HDR sources to displayable output
+export interface ToneMapOptions {
+   operator?: ToneMapOperator; // Default 'reinhard'
+   exposure?: number; // Stops applied before the operator, default 0
+   white_point?: number; // 'reinhard' only: linear value mapped to pure white
+}
+
+// Why the resize step did or did not change an image
+export type ResizeDecision =
+   | 'resized'
+   | 'enlarged'
+   | 'unchanged'
+   | 'enlargement_prevented'
+   | 'skipped_too_small'
+   | 'clamped_to_minimum';
 
 // File processing status
-export type FileStatus = 'pending' | 'processing' | 'success' | 'failed';
+export type FileStatus =
+   | 'pending'
+   | 'processing'
+   | 'success'
+   | 'failed'
+   | 'cached';
+
+// Machine-readable failure reason, for errors the UI handles specially
+export type ErrorCode =
+   | 'image_too_large'
+   | 'decode_limit_exceeded'
+   | 'memory_budget_exceeded';
 
 // Request to optimize a batch of images
 export interface OptimizeBatchRequest {
    paths: string[];
    output_dir: string;
-   format?: OutputFormat; // Only required for 'convert' mode
+   format?: OutputFormat; // Default: the source format, 'jpeg' for HEIF, 'png' for SVG, EXR, HDR and DDS
    overwrite: boolean;
    operation_mode: OperationMode;
    quality?: number; // 0-100, default 75 for WebP, 90 for PNG
@@ -28,6 +165,73 @@ export interface OptimizeBatchRequest {
    max_width?: number; // Optional resize width (when resize_mode = 'dimensions')
    max_height?: number; // Optional resize height (when resize_mode = 'dimensions')
    keep_aspect_ratio?: boolean; // Default true
+   resize_length?: number; // Edge length for 'long_edge' / 'short_edge'
+   background_color?: string; // Padding color for 'contain', '#RRGGBB[AA]', default white
+   alpha_matte?: string; // Background for transparency when converting to JPEG: '#RRGGBB', 'checker' or 'auto', default white
+   allow_enlarge?: boolean; // Allow upscaling, default false; 'cover'/'contain' boxes shrink to the source scale instead
+   min_output_width?: number; // Aspect-preserving resizes never go below this
+   min_output_height?: number;
+   skip_below_width?: number; // Leave smaller images unresized
+   skip_below_height?: number;
+   resize_filter?: ResizeFilter; // Default 'lanczos3'
+   resize_backend?: ResizeBackend; // Default 'standard'
+   sharpen?: SharpenOptions; // Unsharp mask applied after downscaling
+   jpeg?: JpegOptions; // JPEG encoder options
+   webp?: WebpOptions; // WebP encoder options
+   png?: PngOptions; // PNG encoder and oxipng options
+   svg?: SvgOptions; // Raster size and background for SVG sources
+   tone_map?: ToneMapOptions; // How EXR and Radiance HDR sources are brought into display range
+   preserve_bit_depth?: boolean; // Keep 16-bit and float samples when the output format can store them, default true
+   reduce_color_type?: boolean; // Write PNG, TIFF, WebP and BMP in their smallest lossless layout, default true
+   smart_crop?: boolean; // Choose the 'cover' crop window by saliency, default false
+   focal_point?: FocalPoint; // Explicit 'cover' crop center, overrides smart_crop
+   max_pixels?: number; // Refuse larger images, default 250 million
+   max_decode_bytes?: number; // Refuse images needing more memory to decode, default 512 MiB
+   create_backup?: boolean; // Default true when overwrite is true
+   use_cache?: boolean; // Skip files whose output is already up-to-date, default true
+   rules?: SettingsRule[]; // Conditional overrides, applied in order
+   overrides?: Record<string, Partial<ProcessingSettings>>; // Per-path overrides, applied after rules
+}
+
+// Point of interest in normalized coordinates (0-1, origin top-left)
+export interface FocalPoint {
+   x: number;
+   y: number;
+}
+
+// Conditions a file must satisfy for a rule to apply
+export interface RuleMatch {
+   glob?: string; // File name pattern, or full path pattern if it contains a separator
+   formats?: string[]; // Source format by any of its extensions, e.g. 'jpeg' (also .jpg), 'heic', 'svg'
+   min_width?: number;
+   max_width?: number;
+   min_height?: number;
+   max_height?: number;
+   has_alpha?: boolean;
+}
+
+// Settings applied to every file matching the rule
+export interface SettingsRule {
+   match: RuleMatch;
+   settings: Partial<ProcessingSettings>;
+}
+
+// Settings shared by batch requests, watches and presets
+export type ProcessingSettings = Omit<
+   OptimizeBatchRequest,
+   | 'paths'
+   | 'output_dir'
+   | 'overwrite'
+   | 'create_backup'
+   | 'use_cache'
+   | 'overrides'
+>;
+
+// Named settings template managed by the backend
+export interface Preset extends ProcessingSettings {
+   name: string;
+   description?: string | null;
+   builtin?: boolean; // Built-in presets cannot be updated or deleted
 }
 
 // Backup information
@@ -45,7 +249,13 @@ export interface FileResult {
    output_width: number | null;
    output_height: number | null;
    error: string | null;
+   error_code: ErrorCode | null;
    backup_info: BackupInfo | null;
+   settings: ProcessingSettings | null; // Effective settings after rules and overrides
+   applied_rules: number[] | null; // Indices of the rules that matched
+   focal_point: FocalPoint | null; // Crop center used by 'cover' resizing
+   resize_decision: ResizeDecision | null;
+   warnings: string[] | null; // e.g. transparency discarded when converting to JPEG
 }
 
 // Result of the entire batch operation (matches Rust serde output)
@@ -54,6 +264,7 @@ export interface BatchResult {
    total: number;
    success_count: number;
    failed_count: number;
+   cached_count: number;
    backups: BackupInfo[];
 }
 
@@ -63,9 +274,81 @@ export interface ProgressUpdate {
    total: number;
    success_count: number;
    failed_count: number;
+   cached_count: number;
    current_file?: string;
 }
 
+// Request to start watching folders (settings mirror OptimizeBatchRequest)
+export interface WatchRequest
+   extends Omit<OptimizeBatchRequest, 'paths' | 'create_backup' | 'overrides'> {
+   directories: string[];
+   recursive?: boolean; // Default true
+   debounce_ms?: number; // Quiet period before a file is processed, default 1500
+}
+
+// Active watch returned by list_watches
+export interface WatchInfo {
+   id: number;
+   directories: string[];
+   output_dir: string;
+}
+
+// File discovered by a folder scan (matches Rust serde output)
+export interface ScannedFile {
+   path: string;
+   size: number;
+   width: number | null;
+   height: number | null;
+}
+
+// Entry that could not be read during a folder scan
+export interface ScanError {
+   path: string;
+   error: string;
+}
+
+// Streaming folder scan event from backend
+export interface ScanProgress {
+   files: ScannedFile[];
+   found_count: number;
+   error_count: number;
+   scanned_dirs: number;
+   current_dir: string | null;
+}
+
+// Result of a complete (or cancelled) folder scan
+export interface ScanResult {
+   files: ScannedFile[];
+   errors: ScanError[];
+   cancelled: boolean;
+}
+
+// Request for generate_icon_set: a favicon / app icon set from one square source
+export interface IconSetRequest {
+   path: string; // Square source image; SVG sources are rasterized at full size
+   output_dir: string; // The set is written directly into this directory
+   name?: string; // App name in site.webmanifest, default the source file name
+   short_name?: string; // Home screen label, default name
+   theme_color?: string; // '#RRGGBB', default white
+   background_color?: string; // Splash screen and apple-touch-icon background, default white
+   base_path?: string; // URL prefix in the manifest and HTML snippet, default '/'
+   png?: PngOptions;
+}
+
+// One file of a generated icon set
+export interface IconFile {
+   path: string;
+   width: number; // Largest entry for favicon.ico, 0 for non-image files
+   height: number;
+   size: number;
+}
+
+export interface IconSetResult {
+   files: IconFile[];
+   html: string; // <head> snippet, also written to favicons.html
+   warnings: string[] | null;
+}
+
 // Tracked file in the UI
 export interface TrackedFile {
    id: string;
@@ -104,19 +387,6 @@ export interface BackupFile {
    wasOverwritten: boolean;
 }
 
-// Supported image extensions for filtering
-export const SUPPORTED_EXTENSIONS = [
-   '.png',
-   '.jpg',
-   '.jpeg',
-   '.webp',
-   '.tiff',
-   '.tif',
-   '.bmp',
-   '.gif',
-   '.qoi',
-] as const;
-
 // Format display names
 export const FORMAT_OPTIONS: { value: OutputFormat; label: string }[] = [
    { value: 'png', label: 'PNG (Lossy - PngQuant)' },
@@ -125,6 +395,11 @@ export const FORMAT_OPTIONS: { value: OutputFormat; label: string }[] = [
    { value: 'tiff', label: 'TIFF' },
    { value: 'qoi', label: 'QOI (Quite OK Image)' },
    { value: 'bmp', label: 'BMP' },
+   { value: 'tga', label: 'TGA' },
+   { value: 'pnm', label: 'PNM (PGM / PPM / PAM)' },
+   { value: 'ico', label: 'ICO (up to 256×256)' },
+   { value: 'gif', label: 'GIF (static)' },
+   { value: 'avif', label: 'AVIF (Lossy)' },
 ];
 
 // Operation mode display names